@@ -34,6 +34,27 @@ impl AABB {
         }
         true
     }
+
+    // 与 hit 相同，但返回光线在盒内的参数区间
+    pub fn hit_interval(&self, _r: &Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
+        let mut t_min = tmin;
+        let mut t_max = tmax;
+
+        for i in 0..3 {
+            let invd = 1.0 / _r.drc[i];
+            let mut t0 = (self._min[i] - _r.org[i]) * invd;
+            let mut t1 = (self._max[i] - _r.org[i]) * invd;
+            if invd < 0.0 {
+                std::mem::swap(&mut t1, &mut t0);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
 }
 
 pub fn surrounding_box(box0: AABB, box1: AABB) -> AABB {
@@ -0,0 +1,47 @@
+use crate::hittable::get_sphere_uv;
+use crate::ray::Ray;
+use crate::texture::{Solid, Texture};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// 逃逸光线看到的背景：相机光线看到按屏幕坐标贴上的底片，间接光线看到环境（纯色或按方向贴图）
+#[derive(Clone)]
+pub struct Background {
+    pub backplate: Option<Arc<dyn Texture>>,
+    pub environment: Arc<dyn Texture>,
+}
+
+impl Background {
+    pub fn new(color: Vec3) -> Background {
+        Background::with_environment(Arc::new(Solid::new(color)))
+    }
+
+    // 环境贴图按方向的球面坐标取值（与 Sphere 的 uv 约定相同）
+    pub fn with_environment(environment: Arc<dyn Texture>) -> Background {
+        Background {
+            backplate: None,
+            environment,
+        }
+    }
+
+    // 产品图流程：相机直接看到的是照片，照明与反射仍来自 environment
+    #[allow(dead_code)]
+    pub fn with_backplate(
+        backplate: Arc<dyn Texture>,
+        environment: Arc<dyn Texture>,
+    ) -> Background {
+        Background {
+            backplate: Some(backplate),
+            environment,
+        }
+    }
+
+    pub fn value(&self, r: &Ray) -> Vec3 {
+        let drc = r.drc.unit();
+        if let (Some(plate), Some((u, v))) = (&self.backplate, r.screen) {
+            return plate.value(u, v, drc);
+        }
+        let (u, v) = get_sphere_uv(&drc);
+        self.environment.value(u, v, drc)
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(warnings, unused)]
 use crate::random_double_limit;
-use crate::{ray::Ray, vec3::random_in_unit_disk, vec3::Vec3};
+use crate::{ray::Ray, ray::RayDifferential, vec3::random_in_unit_disk, vec3::Vec3};
 use rand::rngs::ThreadRng;
 
 // 对一个像素进行多次采样
@@ -66,4 +66,23 @@ impl Camera {
         );
         return rt;
     }
+
+    // 附带光线微分，da、db 为一个像素对应的 a、b 增量
+    pub fn make_ray_differential(
+        &self,
+        rng: &mut ThreadRng,
+        a: f64,
+        b: f64,
+        da: f64,
+        db: f64,
+    ) -> Ray {
+        let mut rt = self.make_ray(rng, a, b);
+        rt.differential = Some(RayDifferential {
+            rx_org: rt.org,
+            rx_drc: rt.drc + self.hor * da,
+            ry_org: rt.org,
+            ry_drc: rt.drc + self.ver * db,
+        });
+        return rt;
+    }
 }
@@ -0,0 +1,409 @@
+use crate::aabb::AABB;
+use crate::hittable::{Hitrecord, Object};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::{resolve_path, TextureError};
+use crate::vec3::Vec3;
+use std::path::Path;
+use std::sync::Arc;
+
+// 叶结点最多包含 LEAF_CELLS x LEAF_CELLS 个格子，直接逐个求交
+const LEAF_CELLS: usize = 4;
+
+#[derive(Copy, Clone)]
+struct QuadNode {
+    i0: usize,
+    j0: usize,
+    i1: usize,
+    j1: usize,
+    hmin: f64,
+    hmax: f64,
+    children: [usize; 4],
+    n_children: usize,
+}
+
+// 高度场地形：nx * nz 个采样点，每个格子拆成两个三角形
+#[derive(Clone)]
+pub struct Heightfield {
+    pub nx: usize,
+    pub nz: usize,
+    pub corner: Vec3,
+    pub extent_x: f64,
+    pub extent_z: f64,
+    pub mat_ptr: Arc<dyn Material>,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    nodes: Vec<QuadNode>,
+}
+
+impl Heightfield {
+    // 从灰度图读取高度，亮度 [0, 1] 映射为 [0, height_scale]；路径按贴图目录查找
+    pub fn new(
+        path: &Path,
+        corner: Vec3,
+        extent_x: f64,
+        extent_z: f64,
+        height_scale: f64,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Result<Heightfield, TextureError> {
+        let path = resolve_path(path)?;
+        let img = image::open(&path)
+            .map_err(|source| TextureError::Decode { path, source })?
+            .to_luma16();
+        let (w, h) = img.dimensions();
+        // 16 位读取，避免 8 位灰度图在平缓处出现台阶
+        let heights = img.pixels().map(|p| p[0] as f64 / 65535.0).collect();
+        Ok(Heightfield::from_heights(
+            heights,
+            w as usize,
+            h as usize,
+            corner,
+            extent_x,
+            extent_z,
+            height_scale,
+            mat_ptr,
+        ))
+    }
+
+    // heights 按行存储，heights[j * nx + i]，取值会乘以 height_scale
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_heights(
+        heights: Vec<f64>,
+        nx: usize,
+        nz: usize,
+        corner: Vec3,
+        extent_x: f64,
+        extent_z: f64,
+        height_scale: f64,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Heightfield {
+        assert!(nx >= 2 && nz >= 2 && heights.len() == nx * nz);
+        let heights: Vec<f64> = heights.iter().map(|h| h * height_scale).collect();
+        let mut hf = Heightfield {
+            nx,
+            nz,
+            corner,
+            extent_x,
+            extent_z,
+            mat_ptr,
+            heights,
+            normals: Vec::new(),
+            nodes: Vec::new(),
+        };
+        hf.normals = hf.vertex_normals();
+        hf.build(0, 0, nx - 1, nz - 1);
+        hf
+    }
+
+    fn dx(&self) -> f64 {
+        self.extent_x / (self.nx - 1) as f64
+    }
+
+    fn dz(&self) -> f64 {
+        self.extent_z / (self.nz - 1) as f64
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Vec3 {
+        Vec3::new(
+            self.corner.x + i as f64 * self.dx(),
+            self.corner.y + self.height(i, j),
+            self.corner.z + j as f64 * self.dz(),
+        )
+    }
+
+    // 中心差分得到顶点法线，用于插值出平滑的着色法线
+    fn vertex_normals(&self) -> Vec<Vec3> {
+        let mut normals = Vec::with_capacity(self.nx * self.nz);
+        for j in 0..self.nz {
+            for i in 0..self.nx {
+                let il = if i > 0 { i - 1 } else { i };
+                let ir = if i + 1 < self.nx { i + 1 } else { i };
+                let jl = if j > 0 { j - 1 } else { j };
+                let jr = if j + 1 < self.nz { j + 1 } else { j };
+                let dhdx =
+                    (self.height(ir, j) - self.height(il, j)) / ((ir - il) as f64 * self.dx());
+                let dhdz =
+                    (self.height(i, jr) - self.height(i, jl)) / ((jr - jl) as f64 * self.dz());
+                normals.push(Vec3::new(-dhdx, 1.0, -dhdz).unit());
+            }
+        }
+        normals
+    }
+
+    // 建立格子区间 [i0, i1) x [j0, j1) 的 min/max 四叉树，返回结点下标
+    fn build(&mut self, i0: usize, j0: usize, i1: usize, j1: usize) -> usize {
+        let mut node = QuadNode {
+            i0,
+            j0,
+            i1,
+            j1,
+            hmin: f64::INFINITY,
+            hmax: -f64::INFINITY,
+            children: [0; 4],
+            n_children: 0,
+        };
+
+        if i1 - i0 <= LEAF_CELLS && j1 - j0 <= LEAF_CELLS {
+            for j in j0..=j1 {
+                for i in i0..=i1 {
+                    node.hmin = node.hmin.min(self.height(i, j));
+                    node.hmax = node.hmax.max(self.height(i, j));
+                }
+            }
+        } else {
+            let im = if i1 - i0 > LEAF_CELLS {
+                (i0 + i1) / 2
+            } else {
+                i1
+            };
+            let jm = if j1 - j0 > LEAF_CELLS {
+                (j0 + j1) / 2
+            } else {
+                j1
+            };
+            for &(a0, b0, a1, b1) in [
+                (i0, j0, im, jm),
+                (im, j0, i1, jm),
+                (i0, jm, im, j1),
+                (im, jm, i1, j1),
+            ]
+            .iter()
+            {
+                if a1 > a0 && b1 > b0 {
+                    let child = self.build(a0, b0, a1, b1);
+                    node.hmin = node.hmin.min(self.nodes[child].hmin);
+                    node.hmax = node.hmax.max(self.nodes[child].hmax);
+                    node.children[node.n_children] = child;
+                    node.n_children += 1;
+                }
+            }
+        }
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn node_box(&self, node: &QuadNode) -> AABB {
+        AABB::new(
+            &Vec3::new(
+                self.corner.x + node.i0 as f64 * self.dx(),
+                self.corner.y + node.hmin - 0.0001,
+                self.corner.z + node.j0 as f64 * self.dz(),
+            ),
+            &Vec3::new(
+                self.corner.x + node.i1 as f64 * self.dx(),
+                self.corner.y + node.hmax + 0.0001,
+                self.corner.z + node.j1 as f64 * self.dz(),
+            ),
+        )
+    }
+
+    fn hit_node(&self, idx: usize, r: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let node = &self.nodes[idx];
+        self.node_box(node).hit_interval(r, t_min, t_max)?;
+
+        if node.n_children == 0 {
+            let mut closest = t_max;
+            let mut result = None;
+            for j in node.j0..node.j1 {
+                for i in node.i0..node.i1 {
+                    if let Some(rec) = self.hit_cell(i, j, r, t_min, closest) {
+                        closest = rec.t;
+                        result = Some(rec);
+                    }
+                }
+            }
+            return result;
+        }
+
+        // 按进入距离由近到远访问子结点，命中点比下一个子结点更近时提前结束
+        let mut order: Vec<(f64, usize)> = node.children[..node.n_children]
+            .iter()
+            .filter_map(|&c| {
+                self.node_box(&self.nodes[c])
+                    .hit_interval(r, t_min, t_max)
+                    .map(|(t0, _)| (t0, c))
+            })
+            .collect();
+        order.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut closest = t_max;
+        let mut result = None;
+        for (t_enter, c) in order {
+            if t_enter >= closest {
+                break;
+            }
+            if let Some(rec) = self.hit_node(c, r, t_min, closest) {
+                closest = rec.t;
+                result = Some(rec);
+            }
+        }
+        result
+    }
+
+    fn hit_cell(&self, i: usize, j: usize, r: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let ids = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let first = self.hit_triangle([ids[0], ids[1], ids[2]], r, t_min, t_max);
+        let limit = first.as_ref().map_or(t_max, |rec| rec.t);
+        match self.hit_triangle([ids[0], ids[2], ids[3]], r, t_min, limit) {
+            Some(rec) => Some(rec),
+            None => first,
+        }
+    }
+
+    // Moller-Trumbore 三角形求交，法线与 uv 用重心坐标插值
+    fn hit_triangle(
+        &self,
+        ids: [(usize, usize); 3],
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<Hitrecord> {
+        let p0 = self.vertex(ids[0].0, ids[0].1);
+        let p1 = self.vertex(ids[1].0, ids[1].1);
+        let p2 = self.vertex(ids[2].0, ids[2].1);
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pv = Vec3::cross(r.drc, e2);
+        let det = e1 * pv;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv = 1.0 / det;
+        let tv = r.org - p0;
+        let b1 = (tv * pv) * inv;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qv = Vec3::cross(tv, e1);
+        let b2 = (r.drc * qv) * inv;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = (e2 * qv) * inv;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let normal_at = |(i, j): (usize, usize)| self.normals[j * self.nx + i];
+        let n = (normal_at(ids[0]) * b0 + normal_at(ids[1]) * b1 + normal_at(ids[2]) * b2).unit();
+
+        let p = r.at(t);
+        let mut rec = Hitrecord::new(p, n, t, self.mat_ptr.clone());
+        rec.set_face_normal(r, n);
+        rec.set_uv((
+            (p.x - self.corner.x) / self.extent_x,
+            (p.z - self.corner.z) / self.extent_z,
+        ));
+        // u、v 分别沿 x、z 线性变化，偏导为三角形平面内另一坐标不变的方向
+        let ng = Vec3::cross(e1, e2);
+        if ng.y.abs() > 1e-12 {
+            rec.set_dpdu(
+                Vec3::new(1.0, -ng.x / ng.y, 0.0) * self.extent_x,
+                Vec3::new(0.0, -ng.z / ng.y, 1.0) * self.extent_z,
+            );
+        }
+        Some(rec)
+    }
+}
+
+impl Object for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let root = self.nodes.len() - 1;
+        self.hit_node(root, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        let root = &self.nodes[self.nodes.len() - 1];
+        Some(self.node_box(root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::Solid;
+
+    // 9 x 9 个采样点覆盖 [0, 8] x [0, 8]，超过叶结点大小，会建出多层四叉树
+    fn field(height: impl Fn(usize, usize) -> f64, scale: f64) -> Heightfield {
+        let n = 9;
+        let mut heights = Vec::with_capacity(n * n);
+        for j in 0..n {
+            for i in 0..n {
+                heights.push(height(i, j));
+            }
+        }
+        let mat = Arc::new(Lambertian::new(Arc::new(Solid::new(Vec3::ones()))));
+        Heightfield::from_heights(heights, n, n, Vec3::zero(), 8.0, 8.0, scale, mat)
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn test_flat_hit_and_miss() {
+        let hf = field(|_, _| 0.5, 1.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        let rec = hf
+            .hit(
+                &Ray::new(Vec3::new(3.3, 5.0, 4.7), down, 0.0),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!(close(rec.p, Vec3::new(3.3, 0.5, 4.7)));
+        assert!(close(rec.n, Vec3::new(0.0, 1.0, 0.0)));
+        assert!((rec.u - 3.3 / 8.0).abs() < 1e-9 && (rec.v - 4.7 / 8.0).abs() < 1e-9);
+
+        // 从下方射入时命中背面
+        let up = Ray::new(Vec3::new(6.1, -2.0, 1.2), -down, 0.0);
+        let rec = hf.hit(&up, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        // 范围之外、平行于表面、以及 t_max 之前到不了表面
+        let outside = Ray::new(Vec3::new(9.0, 5.0, 4.0), down, 0.0);
+        assert!(hf.hit(&outside, 0.001, f64::INFINITY).is_none());
+        let parallel = Ray::new(Vec3::new(-1.0, 1.0, 4.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(hf.hit(&parallel, 0.001, f64::INFINITY).is_none());
+        let short = Ray::new(Vec3::new(3.3, 5.0, 4.7), down, 0.0);
+        assert!(hf.hit(&short, 0.001, 4.0).is_none());
+    }
+
+    #[test]
+    fn test_ramp_hit_and_miss() {
+        // 高度 y = x / 2，沿 x 方向的斜坡
+        let hf = field(|i, _| i as f64 / 8.0, 4.0);
+        let normal = Vec3::new(-0.5, 1.0, 0.0).unit();
+
+        let rec = hf
+            .hit(
+                &Ray::new(Vec3::new(2.5, 10.0, 3.0), Vec3::new(0.0, -1.0, 0.0), 0.0),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert!(close(rec.p, Vec3::new(2.5, 1.25, 3.0)));
+        assert!(close(rec.n, normal));
+
+        // 斜向光线与斜坡交于 10 - x = x / 2，需要穿过多个结点
+        let oblique = Ray::new(Vec3::new(0.0, 10.0, 4.0), Vec3::new(1.0, -1.0, 0.0), 0.0);
+        let rec = hf.hit(&oblique, 0.001, f64::INFINITY).unwrap();
+        assert!(close(rec.p, Vec3::new(20.0 / 3.0, 10.0 / 3.0, 4.0)));
+
+        // 在斜坡上方离开
+        let away = Ray::new(Vec3::new(0.0, 10.0, 4.0), Vec3::new(1.0, 0.1, 0.0), 0.0);
+        assert!(hf.hit(&away, 0.001, f64::INFINITY).is_none());
+        // 贴着斜坡下方平行穿过
+        let below = Ray::new(Vec3::new(0.0, -0.5, 4.0), Vec3::new(1.0, 0.5, 0.0), 0.0);
+        assert!(hf.hit(&below, 0.001, f64::INFINITY).is_none());
+    }
+}
@@ -1,10 +1,10 @@
 #![allow(warnings, unused)]
 
 use crate::aabb::surrounding_box;
-use crate::material::Isotropic;
+use crate::material::{reflect, refract, Isotropic};
 use crate::random;
 use crate::texture::Texture;
-use crate::{aabb::AABB, material::Material, ray::Ray, vec3::Vec3};
+use crate::{aabb::AABB, material::Material, ray::Ray, ray::RayDifferential, vec3::Vec3};
 use core::f64::consts::PI;
 use rand::Rng;
 use std::ops::Mul;
@@ -24,6 +24,16 @@ pub struct Hitrecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool, //法相与入射方向相反
+    pub dpdu: Vec3,       //p 对 u 的偏导（切线），未知时为零向量
+    pub dpdv: Vec3,       //p 对 v 的偏导
+    pub dndu: Vec3,       //法线 n 对 u、v 的偏导（曲率），平面或未知时为零向量
+    pub dndv: Vec3,
+    pub dpdx: Vec3, //p 对屏幕 x、y 的偏导，由光线微分求得，没有时为零向量
+    pub dpdy: Vec3,
+    pub dudx: f64, //uv 对屏幕的偏导，即纹理的覆盖范围
+    pub dvdx: f64,
+    pub dudy: f64,
+    pub dvdy: f64,
     pub mat_ptr: Arc<dyn Material>,
 }
 
@@ -37,6 +47,16 @@ impl Hitrecord {
             u: 0.0,
             v: 0.0,
             front_face: true,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
+            dndu: Vec3::zero(),
+            dndv: Vec3::zero(),
+            dpdx: Vec3::zero(),
+            dpdy: Vec3::zero(),
+            dudx: 0.0,
+            dvdx: 0.0,
+            dudy: 0.0,
+            dvdy: 0.0,
         }
     }
 
@@ -60,6 +80,116 @@ impl Hitrecord {
         self.u = res.0;
         self.v = res.1;
     }
+
+    pub fn set_dpdu(&mut self, dpdu: Vec3, dpdv: Vec3) {
+        self.dpdu = dpdu;
+        self.dpdv = dpdv;
+    }
+
+    // 相对于当前（朝向入射光线一侧的）法线 n 的偏导
+    pub fn set_dndu(&mut self, dndu: Vec3, dndv: Vec3) {
+        self.dndu = dndu;
+        self.dndv = dndv;
+    }
+
+    // 把两条偏移光线与交点处的切平面求交，再由 dpdu、dpdv 解出 uv 对屏幕的偏导
+    pub fn set_differentials(&mut self, r: &Ray) {
+        let d = match r.differential {
+            Some(d) => d,
+            None => return,
+        };
+        let n = self.n;
+        let on_plane = |org: Vec3, drc: Vec3| {
+            let den = drc * n;
+            if den.abs() < 1e-12 {
+                return None;
+            }
+            Some(org + drc * (((self.p - org) * n) / den))
+        };
+        let (px, py) = match (on_plane(d.rx_org, d.rx_drc), on_plane(d.ry_org, d.ry_drc)) {
+            (Some(px), Some(py)) => (px, py),
+            _ => return,
+        };
+        self.dpdx = px - self.p;
+        self.dpdy = py - self.p;
+
+        // dpdx = dpdu * dudx + dpdv * dvdx 的最小二乘解
+        let a00 = self.dpdu * self.dpdu;
+        let a01 = self.dpdu * self.dpdv;
+        let a11 = self.dpdv * self.dpdv;
+        let det = a00 * a11 - a01 * a01;
+        if det.abs() < 1e-20 {
+            return;
+        }
+        let solve = |dp: Vec3| {
+            let b0 = self.dpdu * dp;
+            let b1 = self.dpdv * dp;
+            ((a11 * b0 - a01 * b1) / det, (a00 * b1 - a01 * b0) / det)
+        };
+        let (dudx, dvdx) = solve(self.dpdx);
+        let (dudy, dvdy) = solve(self.dpdy);
+        self.dudx = dudx;
+        self.dvdx = dvdx;
+        self.dudy = dudy;
+        self.dvdy = dvdy;
+    }
+
+    // 入射光线发生理想镜面反射或折射时，按 pbrt 的做法对出射方向关于屏幕 x、y 求导，
+    // 包含法线随位置变化的曲率项；其余散射丢弃微分
+    pub fn scatter_differential(&self, r_in: &Ray, out: &Ray) -> Option<RayDifferential> {
+        let d = r_in.differential?;
+        if self.dpdx.squared_length() == 0.0 {
+            return None;
+        }
+        let wi = r_in.drc.unit();
+        let wo = out.drc.unit();
+        let n = self.n;
+        let close = |a: Vec3, b: Vec3| (a - b).squared_length() < 1e-8;
+
+        let dndx = self.dndu * self.dudx + self.dndv * self.dvdx;
+        let dndy = self.dndu * self.dudy + self.dndv * self.dvdy;
+        let dwdx = d.rx_drc.unit() - wi;
+        let dwdy = d.ry_drc.unit() - wi;
+        // 入射方向与法线的点积对 x（或 y）的导数
+        let dcos = |dw: Vec3, dn: Vec3| dw * n + wi * dn;
+
+        let (fx, fy): (Vec3, Vec3) = if close(wo, reflect(wi, n)) {
+            // r = w - 2 (w·n) n
+            let dr = |dw: Vec3, dn: Vec3| dw - (n * dcos(dw, dn) + dn * (wi * n)) * 2.0;
+            (wo + dr(dwdx, dndx), wo + dr(dwdy, dndy))
+        } else if wo * n < 0.0 {
+            // 由折射前后的正弦之比得到相对折射率
+            let cos_i = -(wi * n);
+            let cos_t = -(wo * n);
+            let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+            let sin_t = (1.0 - cos_t * cos_t).max(0.0).sqrt();
+            if sin_i < 1e-6 || cos_t < 1e-6 {
+                return None;
+            }
+            let eta = sin_t / sin_i;
+            if !close(wo, refract(wi, n, eta).unit()) {
+                return None;
+            }
+            // t = eta w + mu n，mu = eta cos_i - cos_t
+            let mu = eta * cos_i - cos_t;
+            let dt = |dw: Vec3, dn: Vec3| {
+                let dmu = -(eta - eta * eta * cos_i / cos_t) * dcos(dw, dn);
+                dw * eta + dn * mu + n * dmu
+            };
+            (wo + dt(dwdx, dndx), wo + dt(dwdy, dndy))
+        } else {
+            return None;
+        };
+        if fx.x.is_nan() || fy.x.is_nan() {
+            return None;
+        }
+        Some(RayDifferential {
+            rx_org: self.p + self.dpdx,
+            rx_drc: fx,
+            ry_org: self.p + self.dpdy,
+            ry_drc: fy,
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -101,6 +231,11 @@ impl Object for Sphere {
                 rec.set_face_normal(&r, outward_normal);
                 let res = get_sphere_uv(&((rec.p - self.ct) / self.rd));
                 rec.set_uv(res);
+                let (dpdu, dpdv) = sphere_dpdu(&outward_normal, self.rd);
+                rec.set_dpdu(dpdu, dpdv);
+                // 球面上 n = (p - c) / r，翻转到内侧时偏导同样取反
+                let k = if rec.front_face { 1.0 } else { -1.0 };
+                rec.set_dndu(dpdu * (k / self.rd), dpdv * (k / self.rd));
                 return Some(rec);
             }
 
@@ -114,6 +249,11 @@ impl Object for Sphere {
                 rec.set_face_normal(&r, outward_normal);
                 let res = get_sphere_uv(&rec.p);
                 rec.set_uv(res);
+                let (dpdu, dpdv) = sphere_dpdu(&outward_normal, self.rd);
+                rec.set_dpdu(dpdu, dpdv);
+                // 球面上 n = (p - c) / r，翻转到内侧时偏导同样取反
+                let k = if rec.front_face { 1.0 } else { -1.0 };
+                rec.set_dndu(dpdu * (k / self.rd), dpdv * (k / self.rd));
                 return Some(rec);
             }
         }
@@ -139,6 +279,18 @@ pub fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
     (u, v)
 }
 
+// 与 get_sphere_uv 对应的 (dp/du, dp/dv)，p 为单位球面上的点；两极处退化为零向量
+pub fn sphere_dpdu(p: &Vec3, radius: f64) -> (Vec3, Vec3) {
+    let pi = std::f64::consts::PI;
+    let dpdu = Vec3::new(p.z, 0.0, -p.x) * (2.0 * pi * radius);
+    let cos_theta = (p.x * p.x + p.z * p.z).sqrt();
+    if cos_theta < 1e-9 {
+        return (Vec3::zero(), Vec3::zero());
+    }
+    let dpdv = Vec3::new(-p.y * p.x / cos_theta, cos_theta, -p.y * p.z / cos_theta) * (pi * radius);
+    (dpdu, dpdv)
+}
+
 #[derive(Clone)]
 pub struct Hlist {
     pub objects: Vec<Arc<dyn Object>>,
@@ -240,15 +392,17 @@ impl Object for Xyrect {
         if !flag {
             outward_normal = -outward_normal;
         }
-        Some(Hitrecord {
-            u: (x - self.x0) / (self.x1 - self.x0),
-            v: (y - self.y0) / (self.y1 - self.y0),
-            t,
-            n: outward_normal,
-            front_face: flag,
-            mat_ptr: self.mat_ptr.clone(),
-            p: ray.at(t),
-        })
+        let mut rec = Hitrecord::new(ray.at(t), outward_normal, t, self.mat_ptr.clone());
+        rec.front_face = flag;
+        rec.set_uv((
+            (x - self.x0) / (self.x1 - self.x0),
+            (y - self.y0) / (self.y1 - self.y0),
+        ));
+        rec.set_dpdu(
+            Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            Vec3::new(0.0, self.y1 - self.y0, 0.0),
+        );
+        Some(rec)
     }
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
         Some(AABB::new(
@@ -297,15 +451,17 @@ impl Object for Xzrect {
         if !flag {
             outward_normal = -outward_normal;
         }
-        Some(Hitrecord {
-            u: (x - self.x0) / (self.x1 - self.x0),
-            v: (z - self.z0) / (self.z1 - self.z0),
-            t,
-            n: outward_normal,
-            front_face: flag,
-            mat_ptr: self.mat_ptr.clone(),
-            p: ray.at(t),
-        })
+        let mut rec = Hitrecord::new(ray.at(t), outward_normal, t, self.mat_ptr.clone());
+        rec.front_face = flag;
+        rec.set_uv((
+            (x - self.x0) / (self.x1 - self.x0),
+            (z - self.z0) / (self.z1 - self.z0),
+        ));
+        rec.set_dpdu(
+            Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, self.z1 - self.z0),
+        );
+        Some(rec)
     }
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
         Some(AABB::new(
@@ -354,15 +510,17 @@ impl Object for Yzrect {
         if !flag {
             outward_normal = -outward_normal;
         }
-        Some(Hitrecord {
-            u: (y - self.y0) / (self.y1 - self.y0),
-            v: (z - self.z0) / (self.z1 - self.z0),
-            t,
-            n: outward_normal,
-            front_face: flag,
-            mat_ptr: self.mat_ptr.clone(),
-            p: ray.at(t),
-        })
+        let mut rec = Hitrecord::new(ray.at(t), outward_normal, t, self.mat_ptr.clone());
+        rec.front_face = flag;
+        rec.set_uv((
+            (y - self.y0) / (self.y1 - self.y0),
+            (z - self.z0) / (self.z1 - self.z0),
+        ));
+        rec.set_dpdu(
+            Vec3::new(0.0, self.y1 - self.y0, 0.0),
+            Vec3::new(0.0, 0.0, self.z1 - self.z0),
+        );
+        Some(rec)
     }
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
         Some(AABB::new(
@@ -496,15 +654,9 @@ impl Object for Translate {
             if !flag {
                 rec.n = -rec.n;
             }
-            return Some(Hitrecord {
-                p: rec.p + self.offset,
-                n: rec.n,
-                front_face: flag,
-                mat_ptr: rec.mat_ptr,
-                u: rec.u,
-                t: rec.t,
-                v: rec.v,
-            });
+            rec.p += self.offset;
+            rec.front_face = flag;
+            return Some(rec);
         }
         None
     }
@@ -589,7 +741,7 @@ impl Object for RotateY {
         drc.z = self.sin_theta * ray.drc.x + self.cos_theta * ray.drc.z;
 
         let rotated_r = Ray::new(org, drc, ray.tm);
-        if let Some(rec) = self.ptr.hit(&rotated_r, t_min, t_max) {
+        if let Some(mut rec) = self.ptr.hit(&rotated_r, t_min, t_max) {
             let mut p = rec.p;
             let mut n = rec.n;
 
@@ -599,19 +751,25 @@ impl Object for RotateY {
             n.x = self.cos_theta * rec.n.x + self.sin_theta * rec.n.z;
             n.z = -self.sin_theta * rec.n.x + self.cos_theta * rec.n.z;
 
+            // 切向量与法线的偏导同样绕 y 轴转回世界坐标
+            let rotate = |a: Vec3| {
+                Vec3::new(
+                    self.cos_theta * a.x + self.sin_theta * a.z,
+                    a.y,
+                    -self.sin_theta * a.x + self.cos_theta * a.z,
+                )
+            };
+            rec.set_dpdu(rotate(rec.dpdu), rotate(rec.dpdv));
+            rec.set_dndu(rotate(rec.dndu), rotate(rec.dndv));
+
             let flag = (rotated_r.drc * rec.n) < 0.0;
             if !flag {
                 n = -n;
             }
-            return Some(Hitrecord {
-                p,
-                n,
-                front_face: flag,
-                mat_ptr: rec.mat_ptr,
-                u: rec.u,
-                t: rec.t,
-                v: rec.v,
-            });
+            rec.p = p;
+            rec.n = n;
+            rec.front_face = flag;
+            return Some(rec);
         }
         None
     }
@@ -625,6 +783,30 @@ impl Object for RotateY {
     }
 }
 
+// 翻转物体的正面，例如让单面发光的 Xzrect 朝下发光
+#[derive(Clone)]
+pub struct FlipFace {
+    pub ptr: Arc<dyn Object>,
+}
+
+impl FlipFace {
+    pub fn new(ptr: Arc<dyn Object>) -> Self {
+        Self { ptr }
+    }
+}
+
+impl Object for FlipFace {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let mut rec = self.ptr.hit(ray, t_min, t_max)?;
+        rec.front_face = !rec.front_face;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.ptr.bounding_box(t0, t1)
+    }
+}
+
 #[derive(Clone)]
 pub struct ConstantMedium {
     pub boundary: Arc<dyn Object>,
@@ -640,6 +822,19 @@ impl ConstantMedium {
             neg: -1.0 / d,
         }
     }
+
+    // 使用任意相函数（如 HenyeyGreenstein）代替各向同性散射
+    pub fn new_with_phase(
+        boundary: Arc<dyn Object>,
+        d: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            phase_function,
+            neg: -1.0 / d,
+        }
+    }
 }
 
 impl Object for ConstantMedium {
@@ -674,15 +869,13 @@ impl Object for ConstantMedium {
                 if hit_distance > distance_inside_boundary {
                     return None;
                 }
-                return Some(Hitrecord {
-                    t: rec1.t + hit_distance / ray_length,
-                    p: ray.at(rec1.t + hit_distance / ray_length),
-                    n: Vec3::new(1.0, 0.0, 0.0),
-                    front_face: true,
-                    mat_ptr: self.phase_function.clone(),
-                    u: 0.0,
-                    v: 0.0,
-                });
+                let t = rec1.t + hit_distance / ray_length;
+                return Some(Hitrecord::new(
+                    ray.at(t),
+                    Vec3::new(1.0, 0.0, 0.0),
+                    t,
+                    self.phase_function.clone(),
+                ));
             } else {
                 return None;
             }
@@ -696,6 +889,95 @@ impl Object for ConstantMedium {
     }
 }
 
+// 非均匀介质：密度取自纹理（三个分量的平均值），用 delta tracking 采样散射点
+#[derive(Clone)]
+pub struct HeterogeneousMedium {
+    pub boundary: Arc<dyn Object>,
+    pub phase_function: Arc<dyn Material>,
+    pub density: Arc<dyn Texture>,
+    pub max_density: f64, // 密度上界，必须不小于 density 的最大值
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        boundary: Arc<dyn Object>,
+        density: Arc<dyn Texture>,
+        max_density: f64,
+        a: Arc<dyn Texture>,
+    ) -> HeterogeneousMedium {
+        HeterogeneousMedium {
+            boundary,
+            phase_function: Arc::<Isotropic>::new(Isotropic::new(a)),
+            density,
+            max_density,
+        }
+    }
+
+    pub fn new_with_phase(
+        boundary: Arc<dyn Object>,
+        density: Arc<dyn Texture>,
+        max_density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> HeterogeneousMedium {
+        HeterogeneousMedium {
+            boundary,
+            phase_function,
+            density,
+            max_density,
+        }
+    }
+
+    fn density_at(&self, p: Vec3) -> f64 {
+        let d = self.density.value(0.0, 0.0, p);
+        (d.x + d.y + d.z) / 3.0
+    }
+}
+
+impl Object for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let mut rng = rand::thread_rng();
+
+        let mut rec1 = self.boundary.hit(ray, -f64::INFINITY, f64::INFINITY)?;
+        let mut rec2 = self.boundary.hit(ray, rec1.t + 0.0001, f64::INFINITY)?;
+        if rec1.t < t_min {
+            rec1.t = t_min;
+        }
+        if rec2.t > t_max {
+            rec2.t = t_max;
+        }
+        if rec1.t >= rec2.t {
+            return None;
+        }
+        if rec1.t < 0.0 {
+            rec1.t = 0.0
+        }
+
+        // 以 max_density 为均匀上界采样候选碰撞，再按 density / max_density 接受，
+        // 其余视为虚碰撞继续前进
+        let ray_length = ray.drc.length();
+        let mut t = rec1.t;
+        loop {
+            t -= rng.gen::<f64>().ln() / (self.max_density * ray_length);
+            if t >= rec2.t {
+                return None;
+            }
+            let p = ray.at(t);
+            if rng.gen::<f64>() * self.max_density < self.density_at(p) {
+                return Some(Hitrecord::new(
+                    p,
+                    Vec3::new(1.0, 0.0, 0.0),
+                    t,
+                    self.phase_function.clone(),
+                ));
+            }
+        }
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
 #[derive(Clone)]
 pub struct MovingSphere {
     pub center0: Vec3,
@@ -745,6 +1027,11 @@ impl Object for MovingSphere {
                 rec.set_face_normal(&r, outward_normal);
                 let res = get_sphere_uv(&((rec.p - self.center(r.tm)) / self.radius));
                 rec.set_uv(res);
+                let (dpdu, dpdv) = sphere_dpdu(&outward_normal, self.radius);
+                rec.set_dpdu(dpdu, dpdv);
+                // 球面上 n = (p - c) / r，翻转到内侧时偏导同样取反
+                let k = if rec.front_face { 1.0 } else { -1.0 };
+                rec.set_dndu(dpdu * (k / self.radius), dpdv * (k / self.radius));
                 return Some(rec);
             }
 
@@ -758,6 +1045,11 @@ impl Object for MovingSphere {
                 rec.set_face_normal(&r, outward_normal);
                 let res = get_sphere_uv(&((rec.p - self.center(r.tm)) / self.radius));
                 rec.set_uv(res);
+                let (dpdu, dpdv) = sphere_dpdu(&outward_normal, self.radius);
+                rec.set_dpdu(dpdu, dpdv);
+                // 球面上 n = (p - c) / r，翻转到内侧时偏导同样取反
+                let k = if rec.front_face { 1.0 } else { -1.0 };
+                rec.set_dndu(dpdu * (k / self.radius), dpdv * (k / self.radius));
                 return Some(rec);
             }
         }
@@ -777,3 +1069,35 @@ impl Object for MovingSphere {
         Some(output_box)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::Solid;
+
+    #[test]
+    fn test_sphere_reflection_differential() {
+        // 凸面镜使反射的微分光线发散：与偏移光线在球面上的真实反射方向比较
+        let mat = Arc::new(Lambertian::new(Arc::new(Solid::new(Vec3::ones()))));
+        let sphere = Sphere::new(Vec3::zero(), 1.0, mat);
+        let dx = 0.01;
+        let mut r = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        r.differential = Some(RayDifferential {
+            rx_org: Vec3::new(dx, 0.0, 5.0),
+            rx_drc: r.drc,
+            ry_org: Vec3::new(0.0, dx, 5.0),
+            ry_drc: r.drc,
+        });
+        let mut rec = sphere.hit(&r, 1e-3, f64::INFINITY).unwrap();
+        rec.set_differentials(&r);
+        let out = Ray::new(rec.p, reflect(r.drc, rec.n), 0.0);
+        let d = rec.scatter_differential(&r, &out).unwrap();
+
+        let z = (1.0 - dx * dx).sqrt();
+        let exact = Vec3::new(2.0 * z * dx, 0.0, 2.0 * z * z - 1.0);
+        assert!((d.rx_drc - exact).length() < 1e-3);
+        let exact = Vec3::new(0.0, 2.0 * z * dx, 2.0 * z * z - 1.0);
+        assert!((d.ry_drc - exact).length() < 1e-3);
+    }
+}
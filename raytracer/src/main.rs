@@ -1,42 +1,71 @@
 #[allow(clippy::float_cmp)]
 mod aabb;
+mod background;
 mod bvh;
 mod camera;
+mod heightfield;
 mod hittable;
 mod material;
-// mod perlin;
+mod microfacet;
+mod onb;
+mod perlin;
+mod procedural;
 mod ray;
+mod sdf;
+mod spectrum;
+mod subsurface;
 mod texture;
+mod thinfilm;
 mod vec3;
-use crate::bvh::Boxes;
+mod volume;
+use crate::aabb::AABB;
+use crate::background::Background;
+// use crate::bvh::Boxes;
 use crate::bvh::BvhNode;
-use crate::bvh::ConstantMedium;
+// use crate::bvh::ConstantMedium;
 // use crate::bvh::RotateY;
 // use crate::bvh::Translate;
 use crate::bvh::Xyrect;
 use crate::bvh::Xzrect;
 use crate::bvh::Yzrect;
 use crate::camera::Camera;
+use crate::heightfield::Heightfield;
 use crate::hittable::Arc;
+use crate::hittable::HeterogeneousMedium;
 use crate::hittable::Hitrecord;
 use crate::hittable::Hlist;
 use crate::hittable::Object;
 use crate::hittable::Sphere;
+use crate::material::luminance;
+use crate::material::Conductor;
 use crate::material::Dielectric;
 use crate::material::Diffuse;
 use crate::material::Lambertian;
-use crate::material::Metal;
-// use crate::perlin::Perlin;
+use crate::material::ShadowCatcher;
+// use crate::material::Metal;
+use crate::perlin::Perlin;
+use crate::procedural::{
+    Bricks, ColorRamp, Marble, Ridged, TextureTransform, Wood, Worley, WorleyMode,
+};
 use crate::ray::Ray;
+use crate::sdf::{Sdf, SdfObject, TriPrism};
+use crate::spectrum::{
+    hero_wavelengths, spectrum_to_rgb, upsample_illuminant, upsample_reflectance, Dispersion,
+};
 // use crate::texture::CheckerT;
 use crate::hittable::MovingSphere;
-use crate::texture::ImageTexture;
+use crate::texture::image_or_solid;
 // use crate::texture::Noise;
 // use crate::material::Isotropic;
+use crate::texture::linear_to_srgb;
+use crate::texture::set_texture_dir;
 use crate::texture::Solid;
+use crate::texture::Texture;
+use crate::texture::Turbulence;
 use crate::vec3::Vec3;
+use image::DynamicImage;
 use image::ImageBuffer;
-use image::RgbImage;
+use image::RgbaImage;
 use indicatif::ProgressBar;
 use rand::rngs::ThreadRng;
 use rand::Rng;
@@ -49,6 +78,21 @@ fn main() {
         Ok(x) => x == "true",
         Err(_) => false,
     };
+    // 光谱渲染：SPECTRAL=true 时每条路径携带三个波长，在胶片上积分为 XYZ
+    let is_spectral = match std::env::var("SPECTRAL") {
+        Ok(x) => x == "true",
+        Err(_) => false,
+    };
+    // 透明输出：TRANSPARENT=true 时背景透明，阴影捕捉面只留下阴影，便于合成到照片上
+    let is_transparent = match std::env::var("TRANSPARENT") {
+        Ok(x) => x == "true",
+        Err(_) => false,
+    };
+
+    // 第一个命令行参数为场景目录，相对路径的贴图优先在其中查找
+    if let Some(dir) = std::env::args().nth(1) {
+        set_texture_dir(Path::new(&dir));
+    }
 
     let (n_jobs, n_workers): (usize, usize) = if is_ci { (32, 2) } else { (16, 2) };
 
@@ -61,7 +105,6 @@ fn main() {
     let height = 800;
     let spp = 3000;
     let max_depth = 50;
-    let background = Vec3::zero();
 
     let (tx, rx) = channel();
     let pool = ThreadPool::new(n_workers);
@@ -93,7 +136,15 @@ fn main() {
         1.0,
     );
 
-    let world = cloud();
+    // SCENE 选择场景及其背景：prism 为色散棱镜，procedural 为程序纹理，
+    // shadow_catcher 为阴影捕捉（带天光，便于与照片的环境光匹配），其余为默认的 cloud
+    let scene = std::env::var("SCENE").unwrap_or_default();
+    let (world, background) = match scene.as_str() {
+        "prism" => (prism(), Background::new(Vec3::zero())),
+        "procedural" => (procedural(), Background::new(Vec3::zero())),
+        "shadow_catcher" => (shadow_catcher(), Background::new(Vec3::new(0.3, 0.35, 0.4))),
+        _ => (cloud(), Background::new(Vec3::zero())),
+    };
 
     // let mut _j = image_height - 1;
     // while _j >= 0 {
@@ -139,41 +190,86 @@ fn main() {
     for i in 0..n_jobs {
         let tx = tx.clone();
         let world_ptr = world.clone();
+        let background = background.clone();
         pool.execute(move || {
             let row_begin = height as usize * i / n_jobs;
             let row_end = height as usize * (i + 1) / n_jobs;
             let render_height = row_end - row_begin;
-            let mut img: RgbImage = ImageBuffer::new(width, render_height as u32);
+            let mut img: RgbaImage = ImageBuffer::new(width, render_height as u32);
             for x in 0..width {
                 for (img_y, y) in (row_begin..row_end).enumerate() {
                     let y = y as u32;
                     let mut _s = 0;
                     let mut color = Vec3::new(0.0, 0.0, 0.0);
+                    // 透明输出：物体覆盖度、阴影捕捉面样本数及其上的实际/参考光照
+                    let mut coverage = 0.0;
+                    let mut catcher = 0.0;
+                    let mut full = Vec3::zero();
+                    let mut reference = Vec3::zero();
                     while _s < spp {
                         let _u: f64 = (x as f64 + random_double()) / w_f;
                         let _v: f64 = (y as f64 + random_double()) / h_f;
                         let mut rng: ThreadRng = rand::thread_rng();
-                        let mut _r = cam.make_ray(&mut rng, _u, _v);
-                        color += ray_color(&_r, &background, &world_ptr, max_depth);
+                        let mut _r =
+                            cam.make_ray_differential(&mut rng, _u, _v, 1.0 / w_f, 1.0 / h_f);
+                        // 输出图像的行号向下增长，底片的 v 向上
+                        _r.screen = Some((_u, 1.0 - _v));
+                        let lambdas = if is_spectral {
+                            let lambdas = hero_wavelengths(random_double());
+                            _r.spectral = Some(lambdas);
+                            Some(lambdas)
+                        } else {
+                            None
+                        };
+                        let to_rgb = |radiance: Vec3| match lambdas {
+                            Some(lambdas) => spectrum_to_rgb(radiance, lambdas),
+                            None => radiance,
+                        };
+                        if is_transparent {
+                            let sample = camera_sample(&_r, &background, &world_ptr, max_depth);
+                            color += to_rgb(sample.color);
+                            coverage += sample.alpha;
+                            catcher += sample.catcher;
+                            full += to_rgb(sample.full);
+                            reference += to_rgb(sample.reference);
+                        } else {
+                            color +=
+                                to_rgb(ray_color(&_r, &background, &world_ptr, max_depth, true));
+                        }
                         _s += 1;
                     }
                     let pixel = img.get_pixel_mut(x, img_y as u32);
                     //let color = world_ptr.color(x, y);
                     let sppf = spp as f64;
                     let scale: f64 = 1.0 / sppf;
-                    let mut _r = (color.x * scale).sqrt();
-                    let mut _g = (color.y * scale).sqrt();
-                    let mut _b = (color.z * scale).sqrt();
+                    let mut alpha = 1.0;
+                    if is_transparent {
+                        // 阴影为黑色、不透明度为 1 - 实际/参考；颜色按 alpha 还原为非预乘值
+                        let lit = luminance(reference);
+                        let shadow = if lit > 0.0 {
+                            clamp(1.0 - luminance(full) / lit, 0.0, 1.0)
+                        } else {
+                            0.0
+                        };
+                        alpha = (coverage + catcher * shadow) * scale;
+                        if alpha > 0.0 {
+                            color = color / alpha;
+                        }
+                    }
+                    let mut _r = linear_to_srgb(color.x * scale);
+                    let mut _g = linear_to_srgb(color.y * scale);
+                    let mut _b = linear_to_srgb(color.z * scale);
 
                     let e1 = 256.0 * clamp(_r, 0.0, 0.999);
                     let e2 = 256.0 * clamp(_g, 0.0, 0.999);
                     let e3 = 256.0 * clamp(_b, 0.0, 0.999);
+                    let e4 = 256.0 * clamp(alpha, 0.0, 0.999);
 
                     let _a = e1 as i32;
                     let _b = e2 as i32;
                     let _c = e3 as i32;
                     write_color(&color, spp);
-                    *pixel = image::Rgb([_a as u8, _b as u8, _c as u8]);
+                    *pixel = image::Rgba([_a as u8, _b as u8, _c as u8, e4 as u8]);
                 }
             }
             tx.send((row_begin..row_end, img))
@@ -181,7 +277,7 @@ fn main() {
         });
     }
 
-    let mut img: RgbImage = ImageBuffer::new(width, height);
+    let mut img: RgbaImage = ImageBuffer::new(width, height);
 
     for (rows, data) in rx.iter().take(n_jobs) {
         for (idx, row) in rows.enumerate() {
@@ -194,11 +290,20 @@ fn main() {
         bar.inc(1);
     }
 
-    img.save("output/test.png").unwrap();
+    // 只有透明输出才需要 alpha 通道，否则写出 RGB
+    if is_transparent {
+        img.save("output/test.png").unwrap();
+    } else {
+        DynamicImage::ImageRgba8(img)
+            .to_rgb8()
+            .save("output/test.png")
+            .unwrap();
+    }
     bar.finish();
 }
 
-fn ray_color(_r: &Ray, background: &Vec3, world: &Hlist, depth: i32) -> Vec3 {
+// camera 表示 _r 是否为（或直接穿过表面延续的）相机光线
+fn ray_color(_r: &Ray, background: &Background, world: &Hlist, depth: i32, camera: bool) -> Vec3 {
     let rec: Option<Hitrecord> = world.hit(&*_r, 0.001, std::f64::INFINITY);
 
     if depth <= 0 {
@@ -207,21 +312,24 @@ fn ray_color(_r: &Ray, background: &Vec3, world: &Hlist, depth: i32) -> Vec3 {
     }
 
     match rec {
-        Some(val) => {
-            let emitted = val.mat_ptr.emitted(val.u, val.v, &val.p);
-            let mut rng: ThreadRng = rand::thread_rng();
-            let cur = val.mat_ptr.scatter(&_r, &val, &mut rng);
-            match cur {
-                Some(scattered) => {
-                    Vec3::elemul(
-                        scattered.att,
-                        ray_color(&scattered.ray, &background, &world, depth - 1),
-                    ) + emitted
+        Some(mut val) => {
+            let tr = transmittance(_r, val.t);
+            val.set_differentials(_r);
+            if passes_through(&val, camera) {
+                let color = ray_color(&through(_r, &val), background, world, depth - 1, camera);
+                return Vec3::elemul(tr, color);
+            }
+            let emitted = emission(_r, &val);
+            let color = match scatter(_r, &val) {
+                Some((att, next)) => {
+                    Vec3::elemul(att, ray_color(&next, background, world, depth - 1, false))
+                        + emitted
                 }
                 None => emitted,
-            }
+            };
+            Vec3::elemul(tr, color)
         }
-        None => *background,
+        None => Vec3::elemul(transmittance(_r, f64::INFINITY), sky(_r, background)),
     }
 
     // let unit_drc: Vec3 = _r.drc.unit();
@@ -231,12 +339,175 @@ fn ray_color(_r: &Ray, background: &Vec3, world: &Hlist, depth: i32) -> Vec3 {
     // one * (1.0 - _t) + tmp * _t
 }
 
+// 光线在所处介质（栈顶）中走过 t 这一段的 Beer-Lambert 透射率
+fn transmittance(_r: &Ray, t: f64) -> Vec3 {
+    let medium = match _r.media.and_then(|m| m.top()) {
+        Some(m) => m,
+        None => return Vec3::ones(),
+    };
+    let sigma = match _r.spectral {
+        Some(lambdas) => upsample_reflectance(medium.absorption, lambdas),
+        None => medium.absorption,
+    };
+    let dist = t * _r.drc.length();
+    let tr = |a: f64| if a > 0.0 { (-a * dist).exp() } else { 1.0 };
+    Vec3::new(tr(sigma.x), tr(sigma.y), tr(sigma.z))
+}
+
+// 不可见光源（对相机光线）与镂空处的光线直接穿过表面，不发生散射
+fn passes_through(val: &Hitrecord, camera: bool) -> bool {
+    let opacity = val.mat_ptr.opacity(val);
+    let hidden = camera && !val.mat_ptr.visible_to_camera();
+    hidden || (opacity < 1.0 && random_double() >= opacity)
+}
+
+fn through(_r: &Ray, val: &Hitrecord) -> Ray {
+    let mut through = Ray::new(val.p, _r.drc, _r.tm);
+    through.media = _r.media;
+    through.wavelength = _r.wavelength;
+    through.spectral = _r.spectral;
+    through.screen = _r.screen;
+    through.differential = _r.differential;
+    through
+}
+
+fn emission(_r: &Ray, val: &Hitrecord) -> Vec3 {
+    match _r.spectral {
+        Some(lambdas) => val.mat_ptr.emitted_spectrum(val, lambdas),
+        None => val.mat_ptr.emitted(val),
+    }
+}
+
+fn sky(_r: &Ray, background: &Background) -> Vec3 {
+    match _r.spectral {
+        Some(lambdas) => upsample_illuminant(background.value(_r), lambdas),
+        None => background.value(_r),
+    }
+}
+
+// 在交点处散射，返回衰减与下一条光线（已继承介质、波长等状态）
+fn scatter(_r: &Ray, val: &Hitrecord) -> Option<(Vec3, Ray)> {
+    let mut rng: ThreadRng = rand::thread_rng();
+    let scattered = val.mat_ptr.scatter(_r, val, &mut rng)?;
+    let mut next = scattered.ray;
+    // 材质没有改变介质栈时，散射光线仍处在入射光线所在的介质中
+    if next.media.is_none() {
+        next.media = _r.media;
+    }
+    if next.wavelength.is_none() {
+        next.wavelength = _r.wavelength;
+    }
+    next.differential = val.scatter_differential(_r, &next);
+    let mut att = scattered.att;
+    if let Some(lambdas) = _r.spectral {
+        next.spectral = Some(lambdas);
+        // 材质已按波长给出的衰减不再上采样，其余视为 RGB 反射率
+        if !scattered.spectral {
+            att = upsample_reflectance(att, lambdas);
+        }
+        // 色散后各波长的路径不再相同，只保留主波长（除以其概率 1/3）
+        if _r.wavelength.is_none() && next.wavelength.is_some() {
+            att = Vec3::elemul(att, Vec3::new(3.0, 0.0, 0.0));
+        }
+    }
+    Some((att, next))
+}
+
+// 参考光照：场景中只保留阴影捕捉面与光源，即没有插入物体时地面接收到的光
+fn reference_color(_r: &Ray, background: &Background, world: &Hlist, depth: i32) -> Vec3 {
+    if depth <= 0 {
+        return Vec3::zero();
+    }
+    match world.hit(_r, 0.001, f64::INFINITY) {
+        Some(val) => {
+            let emitted = emission(_r, &val);
+            if val.mat_ptr.is_shadow_catcher() {
+                return match scatter(_r, &val) {
+                    Some((att, next)) => {
+                        Vec3::elemul(att, reference_color(&next, background, world, depth - 1))
+                            + emitted
+                    }
+                    None => emitted,
+                };
+            }
+            if emitted.squared_length() > 0.0 {
+                return emitted;
+            }
+            reference_color(&through(_r, &val), background, world, depth - 1)
+        }
+        None => sky(_r, background),
+    }
+}
+
+// 透明输出时的一个相机样本：物体的颜色与覆盖度，以及阴影捕捉面上同一方向的实际/参考光照
+struct CameraSample {
+    color: Vec3,
+    alpha: f64,
+    catcher: f64,
+    full: Vec3,
+    reference: Vec3,
+}
+
+impl CameraSample {
+    fn empty() -> CameraSample {
+        CameraSample {
+            color: Vec3::zero(),
+            alpha: 0.0,
+            catcher: 0.0,
+            full: Vec3::zero(),
+            reference: Vec3::zero(),
+        }
+    }
+}
+
+fn camera_sample(_r: &Ray, background: &Background, world: &Hlist, depth: i32) -> CameraSample {
+    let mut ray = *_r;
+    for d in 0..depth {
+        let mut val = match world.hit(&ray, 0.001, f64::INFINITY) {
+            Some(val) => val,
+            None => break,
+        };
+        val.set_differentials(&ray);
+        if passes_through(&val, true) {
+            ray = through(&ray, &val);
+            continue;
+        }
+        let depth = depth - d;
+        if !val.mat_ptr.is_shadow_catcher() {
+            let emitted = emission(&ray, &val);
+            let color = match scatter(&ray, &val) {
+                Some((att, next)) => {
+                    Vec3::elemul(att, ray_color(&next, background, world, depth - 1, false))
+                        + emitted
+                }
+                None => emitted,
+            };
+            return CameraSample {
+                color,
+                alpha: 1.0,
+                ..CameraSample::empty()
+            };
+        }
+        // 两次追踪使用同一散射方向，比值的噪声远小于分别采样
+        return match scatter(&ray, &val) {
+            Some((_, next)) => CameraSample {
+                catcher: 1.0,
+                full: ray_color(&next, background, world, depth - 1, false),
+                reference: reference_color(&next, background, world, depth - 1),
+                ..CameraSample::empty()
+            },
+            None => CameraSample::empty(),
+        };
+    }
+    CameraSample::empty()
+}
+
 fn write_color(_s: &Vec3, spp: i32) {
     let sppf = spp as f64;
     let scale: f64 = 1.0 / sppf;
-    let _r = (scale * _s.x).sqrt();
-    let _g = (scale * _s.y).sqrt();
-    let _b = (scale * _s.z).sqrt();
+    let _r = linear_to_srgb(scale * _s.x);
+    let _g = linear_to_srgb(scale * _s.y);
+    let _b = linear_to_srgb(scale * _s.z);
 
     let e1 = 256.0 * clamp(_r, 0.0, 0.999);
     let e2 = 256.0 * clamp(_g, 0.0, 0.999);
@@ -393,7 +664,7 @@ fn random() -> Vec3 {
     let path = Path::new("input.jpg");
     let mut objects = Hlist::new(true);
 
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     objects.push(Arc::<Sphere>::new(Sphere::new(
         Vec3::new(0.0, 0.0, 0.0),
         2.0,
@@ -563,12 +834,12 @@ fn random() -> Vec3 {
 
     // background picture moon
     let path = Path::new("moon.jpg");
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     let moon = Arc::<Lambertian>::new(Lambertian::new(imgtext));
 
     // sky
     let path_sky = Path::new("sky.jpg");
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path_sky));
+    let imgtext = image_or_solid(path_sky, Vec3::new(1.0, 0.0, 1.0));
     let sky = Arc::<Lambertian>::new(Lambertian::new(imgtext));
 
     objects.push(Arc::<Yzrect>::new(Yzrect::new(
@@ -642,7 +913,7 @@ fn random() -> Vec3 {
     // cloud
     let path = Path::new("cloud.jpg");
 
-    let imgtext1 = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext1 = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     objects.push(Arc::<Sphere>::new(Sphere::new(
         Vec3::new(220.0, 280.0, 300.0),
         70.0,
@@ -694,7 +965,7 @@ fn random() -> Vec3 {
     // earth
     let path = Path::new("input.jpg");
 
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     objects.push(Arc::<Sphere>::new(Sphere::new(
         Vec3::new(410.0, 200.0, 400.0),
         100.0,
@@ -735,14 +1006,161 @@ fn random() -> Vec3 {
     objects
 } */
 
+fn prism() -> Hlist {
+    // 细长条形光源照射色散三棱镜，棱镜轴沿 x 方向
+    let mut objects = Hlist::new(true);
+
+    let vl = Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 1.0, 1.0)));
+    let light = Arc::<Diffuse>::new(Diffuse::new_with_strength(vl, 30.0));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        100.0, 456.0, 270.0, 290.0, 554.0, light,
+    )));
+
+    let vw = Arc::<Solid>::new(Solid::new(Vec3::new(0.73, 0.73, 0.73)));
+    let white = Arc::<Lambertian>::new(Lambertian::new(vw));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        -1000.0,
+        1555.0,
+        -1000.0,
+        1555.0,
+        0.0,
+        white.clone(),
+    )));
+    objects.push(Arc::<Xyrect>::new(Xyrect::new(
+        -1000.0, 1555.0, -1000.0, 1555.0, 555.0, white,
+    )));
+
+    let glass = Arc::<Dielectric>::new(Dielectric::new_dispersive(Dispersion::Cauchy {
+        a: 1.6,
+        b: 0.05,
+    }));
+    let tp = TriPrism::new(120.0, 200.0);
+    let ct = Vec3::new(278.0, 300.0, 280.0);
+    let sdf = Arc::new(move |p: Vec3| {
+        let q = p - ct;
+        tp.distance(Vec3::new(q.z, q.y, q.x))
+    });
+    let bbox = AABB::new(
+        &Vec3::new(70.0, 200.0, 170.0),
+        &Vec3::new(490.0, 400.0, 390.0),
+    );
+    objects.push(Arc::<SdfObject>::new(SdfObject::new(sdf, bbox, glass)));
+
+    objects
+}
+
+fn procedural() -> Hlist {
+    // 程序纹理组合：大理石的纹路颜色本身是 Worley 格子，地面为砖墙
+    let mut objects = Hlist::new(true);
+
+    let vl = Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 1.0, 1.0)));
+    let light = Arc::<Diffuse>::new(Diffuse::new_with_strength(vl, 15.0));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        113.0, 443.0, 127.0, 432.0, 554.0, light,
+    )));
+
+    let mut bricks = Bricks::new(
+        60.0,
+        25.0,
+        3.0,
+        Arc::new(Solid::new(Vec3::new(0.55, 0.2, 0.12))),
+        Arc::new(Solid::new(Vec3::new(0.7, 0.7, 0.65))),
+    );
+    bricks.transform = TextureTransform::new(
+        Vec3::zero(),
+        Vec3::new(std::f64::consts::FRAC_PI_2, 0.0, 0.0),
+        Vec3::ones(),
+    );
+    let ground = Arc::<Lambertian>::new(Lambertian::new(Arc::new(bricks)));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        -1000.0, 1555.0, -1000.0, 1555.0, 0.0, ground,
+    )));
+
+    let mut cells = Worley::new(
+        WorleyMode::Cell,
+        Arc::new(Solid::new(Vec3::new(0.1, 0.3, 0.2))),
+        Arc::new(Solid::new(Vec3::new(0.05, 0.1, 0.3))),
+    );
+    cells.transform = TextureTransform::uniform(0.05);
+    let mut marble = Marble::new(
+        Perlin::new(),
+        Arc::new(Solid::new(Vec3::new(0.9, 0.9, 0.85))),
+        Arc::new(cells),
+    );
+    marble.transform = TextureTransform::uniform(0.02);
+    objects.push(Arc::<Sphere>::new(Sphere::new(
+        Vec3::new(160.0, 100.0, 278.0),
+        100.0,
+        Arc::<Lambertian>::new(Lambertian::new(Arc::new(marble))),
+    )));
+
+    let mut wood = Wood::new(
+        Perlin::new(),
+        0.15,
+        Arc::new(Solid::new(Vec3::new(0.75, 0.5, 0.3))),
+        Arc::new(Solid::new(Vec3::new(0.4, 0.22, 0.1))),
+    );
+    wood.transform.offset = Vec3::new(400.0, 0.0, 278.0);
+    let ramp = ColorRamp::from_colors(
+        Arc::new(Ridged::new(
+            Perlin::new(),
+            6,
+            Arc::new(Solid::new(Vec3::zero())),
+            Arc::new(Solid::new(Vec3::ones())),
+        )),
+        &[
+            (0.3, Vec3::new(0.1, 0.1, 0.4)),
+            (0.9, Vec3::new(0.9, 0.8, 0.3)),
+        ],
+    );
+    objects.push(Arc::<Sphere>::new(Sphere::new(
+        Vec3::new(400.0, 100.0, 278.0),
+        100.0,
+        Arc::<Lambertian>::new(Lambertian::new(Arc::new(wood))),
+    )));
+    objects.push(Arc::<Sphere>::new(Sphere::new(
+        Vec3::new(278.0, 300.0, 278.0),
+        80.0,
+        Arc::<Lambertian>::new(Lambertian::new(Arc::new(ramp))),
+    )));
+
+    objects
+}
+
+fn shadow_catcher() -> Hlist {
+    // 合成到照片上：地面为阴影捕捉面，TRANSPARENT=true 渲染后直接叠加到底片上
+    let mut objects = Hlist::new(true);
+
+    let vl = Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 1.0, 1.0)));
+    let light = Arc::<Diffuse>::new(Diffuse::new_with_strength(vl, 15.0));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        113.0, 443.0, 127.0, 432.0, 554.0, light,
+    )));
+
+    let vg = Arc::<Solid>::new(Solid::new(Vec3::new(0.5, 0.5, 0.5)));
+    let ground = Arc::<ShadowCatcher>::new(ShadowCatcher::new(vg));
+    objects.push(Arc::<Xzrect>::new(Xzrect::new(
+        -1000.0, 1555.0, -1000.0, 1555.0, 0.0, ground,
+    )));
+
+    let vr = Arc::<Solid>::new(Solid::new(Vec3::new(0.8, 0.2, 0.1)));
+    let red = Arc::<Lambertian>::new(Lambertian::new(vr));
+    objects.push(Arc::<Sphere>::new(Sphere::new(
+        Vec3::new(278.0, 120.0, 278.0),
+        120.0,
+        red,
+    )));
+
+    objects
+}
+
 fn cloud() -> Hlist {
     let mut objects = Hlist::new(true);
     let mut group = Hlist::new(true);
-    let mut boxes1 = Hlist::new(true);
 
     // light
-    let vl = Arc::<Solid>::new(Solid::new(Vec3::new(7.0, 7.0, 7.0)));
-    let light = Arc::<Diffuse>::new(Diffuse::new(vl));
+    let vl = Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 1.0, 1.0)));
+    let light = Arc::<Diffuse>::new(Diffuse::new_with_strength(vl, 7.0));
     objects.push(Arc::<Xzrect>::new(Xzrect::new(
         203.0, 343.0, 227.0, 332.0, 554.0, light,
     )));
@@ -754,42 +1172,38 @@ fn cloud() -> Hlist {
     let vb = Arc::<Solid>::new(Solid::new(Vec3::new(0.184, 0.2157, 0.4588)));
     let blue = Arc::<Lambertian>::new(Lambertian::new(vb));
 
-    let boxex_per_side = 20;
-    for _i in 0..boxex_per_side {
-        for _j in 0..boxex_per_side {
-            let w: f64 = 100.0;
-            let x0: f64 = -1000.0 + w * _i as f64;
-            let z0: f64 = -1000.0 + w * _j as f64;
-            let y0: f64 = 0.0;
-            let x1: f64 = x0 + w;
-            let y1: f64 = random_double_limit(1.0, 101.0);
-            let z1: f64 = z0 + w;
-
-            /* let v1 = Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 1.0, 1.0)));
-
-            let box1 = Arc::<Boxes>::new(Boxes::new(
-                &Vec3::new(x0, y0, z0),
-                &Vec3::new(x1, y1, z1),
-                ground.clone(),
-            ));
-            let box1 = Arc::<RotateY>::new(RotateY::new(box1.clone(), 0.0));
-            let box1 =
-                Arc::<Translate>::new(Translate::new(box1.clone(), &Vec3::new(0.0, 0.0, 0.0)));
-            let box1 = Arc::<ConstantMedium>::new(ConstantMedium::new(box1.clone(), 0.01, v1));
-            boxes1.push(box1); */
-
-            boxes1.push(Arc::<Boxes>::new(Boxes::new(
-                &Vec3::new(x0, y0, z0),
-                &Vec3::new(x1, y1, z1),
-                ground.clone(),
-            )));
+    // 起伏的地面：优先读取灰度高度图，找不到时用 Perlin 噪声生成，高度在 [0, 100] 之间
+    let corner = Vec3::new(-1000.0, 0.0, -1000.0);
+    let terrain = match Heightfield::new(
+        Path::new("heightmap.png"),
+        corner,
+        2000.0,
+        2000.0,
+        100.0,
+        ground.clone(),
+    ) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("warning: {}", e);
+            let noise = Perlin::new();
+            let samples = 41;
+            let mut heights = Vec::with_capacity(samples * samples);
+            for _j in 0..samples {
+                for _i in 0..samples {
+                    let p = Vec3::new(_i as f64, 0.0, _j as f64) * (50.0 / 150.0);
+                    heights.push(0.5 + 0.5 * noise.noise(p));
+                }
+            }
+            Heightfield::from_heights(
+                heights, samples, samples, corner, 2000.0, 2000.0, 100.0, ground,
+            )
         }
-    }
-    objects.push(Arc::<BvhNode>::new(BvhNode::new_list(boxes1, 0.0, 1.0)));
+    };
+    objects.push(Arc::new(terrain));
 
     // background picture moon
     let path = Path::new("moon.jpg");
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     let moon = Arc::<Lambertian>::new(Lambertian::new(imgtext));
 
     objects.push(Arc::<Yzrect>::new(Yzrect::new(
@@ -833,9 +1247,15 @@ fn cloud() -> Hlist {
     //let _vanan = Arc::<Solid>::new(Solid::new(Vec3::new(0.4, 0.4, 0.4)));
 
     let path = Path::new("surface.jpg");
-    let imgtext = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     let surface = Arc::<Diffuse>::new(Diffuse::new(imgtext));
 
+    // 云团的密度随湍流噪声起伏，在 [0.05, 0.4] 之间
+    let density: Arc<dyn Texture> = Arc::new(ColorRamp::from_colors(
+        Arc::new(Turbulence::new(Perlin::new(), 0.05, 7)),
+        &[(0.0, Vec3::ones() * 0.05), (1.0, Vec3::ones() * 0.4)],
+    ));
+
     // 1左下角
     let boundary = Arc::<Sphere>::new(Sphere::new(
         Vec3::new(112.0, 201.0, 155.0),
@@ -843,23 +1263,25 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.98))),
     )));
 
     // 2右下角
     // cloud
     let path = Path::new("Mercury.jpg");
-    let imgtext1 = Arc::<ImageTexture>::new(ImageTexture::new(path));
+    let imgtext1 = image_or_solid(path, Vec3::new(1.0, 0.0, 1.0));
     let cloud = Arc::<Diffuse>::new(Diffuse::new(imgtext1));
 
     let boundary = Arc::<Sphere>::new(Sphere::new(Vec3::new(433.0, 171.0, 180.0), 40.0, cloud));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.98))),
     )));
 
@@ -884,9 +1306,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.99))),
     )));
 
@@ -897,9 +1320,10 @@ fn cloud() -> Hlist {
         Arc::<Dielectric>::new(Dielectric::new(3.0)),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.976))),
     )));
 
@@ -910,9 +1334,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(van)),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.966))),
     )));
 
@@ -920,12 +1345,16 @@ fn cloud() -> Hlist {
     let boundary = Arc::<Sphere>::new(Sphere::new(
         Vec3::new(327.0, 173.0, 150.0),
         38.0,
-        Arc::<Metal>::new(Metal::new(Vec3::new(0.98, 0.98, 0.98), 10.0)),
+        Arc::<Conductor>::new(Conductor::from_reflectance(
+            Vec3::new(0.98, 0.98, 0.98),
+            0.6,
+        )),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.98))),
     )));
 
@@ -936,9 +1365,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.98))),
     )));
 
@@ -949,18 +1379,20 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.956))),
     )));
 
     // 8
     let boundary = Arc::<Sphere>::new(Sphere::new(Vec3::new(229.0, 249.0, 110.0), 55.0, surface));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.926))),
     )));
 
@@ -971,9 +1403,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.926))),
     )));
 
@@ -983,9 +1416,10 @@ fn cloud() -> Hlist {
         35.0,
         Arc::<Dielectric>::new(Dielectric::new(3.0)),
     ));
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.936))),
     )));
 
@@ -1009,9 +1443,10 @@ fn cloud() -> Hlist {
         Arc::<Dielectric>::new(Dielectric::new(1.5)),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(1.0, 0.98, 0.95))),
     )));
 
@@ -1022,9 +1457,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v.clone())),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.986))),
     )));
 
@@ -1035,9 +1471,10 @@ fn cloud() -> Hlist {
         Arc::<Diffuse>::new(Diffuse::new(v)),
     ));
     group.push(boundary.clone());
-    group.push(Arc::<ConstantMedium>::new(ConstantMedium::new(
+    group.push(Arc::<HeterogeneousMedium>::new(HeterogeneousMedium::new(
         boundary,
-        0.2,
+        density.clone(),
+        0.4,
         Arc::<Solid>::new(Solid::new(Vec3::new(0.98, 0.98, 0.956))),
     )));
 
@@ -1050,7 +1487,7 @@ fn cloud() -> Hlist {
         0.0,
         1.0,
         45.0,
-        Arc::<Metal>::new(Metal::new(Vec3::new(0.7, 0.3, 0.1), 10.0)),
+        Arc::<Conductor>::new(Conductor::from_reflectance(Vec3::new(0.7, 0.3, 0.1), 0.6)),
     )));
 
     objects.push(Arc::<BvhNode>::new(BvhNode::new_list(group, 0.0, 1.0)));
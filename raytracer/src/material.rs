@@ -1,23 +1,57 @@
 use crate::bvh::random_in_unit_sphere;
-use crate::texture::Texture;
+use crate::clamp;
+use crate::microfacet::{
+    anisotropic_alpha, conductor_k, cosine_average, fresnel_conductor, fresnel_dielectric,
+    fresnel_schlick, reflect_about, roughness_to_alpha, Ggx, GgxAlbedo,
+};
+use crate::onb::Onb;
+use crate::spectrum::{
+    blackbody_rgb, sample_wavelength, upsample_illuminant, upsample_reflectance, Blackbody,
+    Dispersion, Illuminant,
+};
+use crate::texture::{Solid, Texture};
 use crate::{
     hittable::Hitrecord,
-    ray::Ray,
+    ray::{MediumEntry, MediumStack, Ray},
     vec3::{random_unit_vector, Vec3},
 };
 use rand::rngs::ThreadRng;
 use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
 
 #[derive(Copy, Clone)]
 pub struct Scatter {
     pub att: Vec3, // 光线衰减率
     pub ray: Ray,
+    pub spectral: bool, // 为 true 时 att 已按入射光线的波长给出，积分器不再由 RGB 上采样
 }
 
 impl Scatter {
     pub fn new(att: Vec3, ray: Ray) -> Scatter {
-        Scatter { att, ray }
+        Scatter {
+            att,
+            ray,
+            spectral: false,
+        }
+    }
+
+    // 由多个分量组合出的衰减（权重可能大于 1）需由材质自行按波长计算
+    pub fn new_spectral(att: Vec3, ray: Ray) -> Scatter {
+        Scatter {
+            att,
+            ray,
+            spectral: true,
+        }
+    }
+}
+
+// 光谱模式下把 RGB 反射率上采样到光线携带的波长，否则原样返回
+pub fn spectral_reflectance(c: Vec3, r_in: &Ray) -> Vec3 {
+    match r_in.spectral {
+        Some(lambdas) => upsample_reflectance(c, lambdas),
+        None => c,
     }
 }
 
@@ -34,9 +68,45 @@ pub fn refract(uv: Vec3, n: Vec3, eoe: f64) -> Vec3 {
     r_out_parallel + r_out_perp
 }
 
+// 界面反射率：参数为（微表面上的）入射角余弦、入射侧折射率和另一侧的复折射率 eta + ik，
+// 返回值按入射光线给出（光谱模式下为三个波长，否则为 RGB）
+pub type FresnelFn<'a> = &'a dyn Fn(f64, f64, Vec3, Vec3) -> Vec3;
+
 pub trait Material: Send + Sync {
     fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter>;
-    fn emitted(&self, u: f64, v: f64, p: &Vec3) -> Vec3;
+    fn emitted(&self, rec: &Hitrecord) -> Vec3;
+
+    // 以 fresnel 代替自身界面的反射率散射，供薄膜等改变界面反射率的包装材质使用；
+    // 没有明确界面的材质忽略 fresnel
+    fn scatter_with_fresnel(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        _fresnel: FresnelFn,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        self.scatter(_r_in, rec, _rng)
+    }
+
+    // 光谱模式下在 lambdas 三个波长处的发光值，默认由 RGB 发光颜色上采样
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        upsample_illuminant(self.emitted(rec), lambdas)
+    }
+
+    // 不透明度，小于 1 时积分器按概率让光线直接穿过（镂空贴图）
+    fn opacity(&self, _rec: &Hitrecord) -> f64 {
+        1.0
+    }
+
+    // 为 false 时相机光线直接穿过该表面（用于不可见的光源）
+    fn visible_to_camera(&self) -> bool {
+        true
+    }
+
+    // 阴影捕捉面：透明输出时只记录其上的阴影，不贡献颜色
+    fn is_shadow_catcher(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone)]
@@ -55,12 +125,12 @@ impl Material for Lambertian {
     fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
         let s_drc: Vec3 = rec.n + random_unit_vector(_rng);
         let sed = Ray::new(rec.p, s_drc, _r_in.tm);
-        let att = self.albedo.value(rec.u, rec.v, rec.p);
+        let att = self.albedo.value_at(rec);
         let rt = Scatter::new(att, sed);
         Some(rt)
     }
 
-    fn emitted(&self, _u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
         Vec3 {
             x: 0.0,
             y: 0.0,
@@ -77,6 +147,7 @@ pub struct Metal {
 }
 
 impl Metal {
+    #[allow(dead_code)]
     pub fn new(a: Vec3, fuzz: f64) -> Metal {
         Metal {
             albedo: Vec3 {
@@ -107,7 +178,7 @@ impl Material for Metal {
         }
     }
 
-    fn emitted(&self, _u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
         Vec3 {
             x: 0.0,
             y: 0.0,
@@ -119,6 +190,74 @@ impl Material for Metal {
 #[derive(Copy, Clone)] // 玻璃 只发生折射
 pub struct Dielectric {
     ref_idx: f64,
+    pub dispersion: Option<Dispersion>, // 色散时光线在首次折射处采样波长
+    pub absorption: Vec3,               // 内部每单位距离的吸收系数（Beer-Lambert）
+    pub priority: i32,                  // 嵌套时优先级高的介质占据重叠区域
+    id: usize,
+}
+
+static NEXT_MEDIUM_ID: AtomicUsize = AtomicUsize::new(1);
+
+// 首次遇到色散介质时采样波长，之后整条路径都只携带这一波长，返回波长与 RGB 权重
+// 光谱模式下取主波长，其余波长由积分器终止
+fn dispersion_wavelength(
+    r_in: &Ray,
+    dispersion: Option<Dispersion>,
+    rng: &mut ThreadRng,
+) -> (Option<f64>, Vec3) {
+    if r_in.wavelength.is_some() || dispersion.is_none() {
+        return (r_in.wavelength, Vec3::ones());
+    }
+    match r_in.spectral {
+        Some(lambdas) => (Some(lambdas.x), Vec3::ones()),
+        None => {
+            let (lambda, weight) = sample_wavelength(rng.gen::<f64>());
+            (Some(lambda), weight)
+        }
+    }
+}
+
+// 每个透射材质在介质栈中的唯一标识
+pub fn next_medium_id() -> usize {
+    NEXT_MEDIUM_ID.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
+// 光线穿过 entry 所属的界面：从正面进入时压栈、从背面离开时出栈，返回透射后的介质栈
+// 与（入射侧，透射侧）的折射率；被更高优先级介质覆盖的界面视为不存在，折射率为 None
+pub fn cross_interface(
+    r_in: &Ray,
+    rec: &Hitrecord,
+    entry: MediumEntry,
+    wavelength: Option<f64>,
+) -> (MediumStack, Option<(f64, f64)>) {
+    let stack = r_in.media.unwrap_or_else(MediumStack::new);
+    let current = stack.top();
+    let hidden = matches!(current, Some(m) if m.priority > entry.priority);
+    let own_ior = entry.ior_at(wavelength);
+    let mut inner = stack;
+    if rec.front_face {
+        inner.push(entry);
+        if hidden {
+            return (inner, None);
+        }
+        let n1 = current.map_or(1.0, |m| m.ior_at(wavelength));
+        (inner, Some((n1, own_ior)))
+    } else {
+        inner.remove(entry.id);
+        if stack.contains(entry.id) && hidden {
+            return (inner, None);
+        }
+        let n2 = inner.top().map_or(1.0, |m| m.ior_at(wavelength));
+        (inner, Some((own_ior, n2)))
+    }
+}
+
+// 被覆盖的界面：光线不改变方向，只更新介质栈
+fn pass_through(r_in: &Ray, rec: &Hitrecord, inner: MediumStack, wavelength: Option<f64>) -> Ray {
+    let mut sed = Ray::new(rec.p, r_in.drc, r_in.tm);
+    sed.media = Some(inner);
+    sed.wavelength = wavelength;
+    sed
 }
 
 pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
@@ -129,19 +268,74 @@ pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
 
 impl Dielectric {
     pub fn new(ref_idx: f64) -> Dielectric {
-        Dielectric { ref_idx }
+        Dielectric::new_nested(ref_idx, Vec3::zero(), 0)
+    }
+
+    // 有色玻璃：absorption 越大颜色越深，例如 (0.0, 0.02, 0.05) 偏红
+    #[allow(dead_code)]
+    pub fn new_absorbing(ref_idx: f64, absorption: Vec3) -> Dielectric {
+        Dielectric::new_nested(ref_idx, absorption, 0)
+    }
+
+    // 杯子里的水应使用比玻璃更低的 priority，冰块高于水
+    pub fn new_nested(ref_idx: f64, absorption: Vec3, priority: i32) -> Dielectric {
+        Dielectric {
+            ref_idx,
+            dispersion: None,
+            absorption,
+            priority,
+            id: next_medium_id(),
+        }
+    }
+
+    // 棱镜、钻石：RGB 光线使用 d 线（587.6 nm）处的折射率
+    pub fn new_dispersive(dispersion: Dispersion) -> Dielectric {
+        let mut d = Dielectric::new(dispersion.ior(587.6));
+        d.dispersion = Some(dispersion);
+        d
+    }
+
+    fn entry(&self) -> MediumEntry {
+        MediumEntry {
+            id: self.id,
+            priority: self.priority,
+            ior: self.ref_idx,
+            dispersion: self.dispersion,
+            absorption: self.absorption,
+        }
     }
 }
 
-impl Material for Dielectric {
-    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
-        let att = Vec3::new(1.0, 1.0, 1.0);
-        let eoe = if rec.front_face {
-            1.0 / self.ref_idx
-        } else {
-            self.ref_idx
+impl Dielectric {
+    // fresnel 为 None 时用 schlick 近似
+    fn scatter_interface(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: Option<FresnelFn>,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        let stack = _r_in.media.unwrap_or_else(MediumStack::new);
+
+        // 介质内的吸收由积分器按每段光线计算，这里只处理界面
+        let (wavelength, weight) = dispersion_wavelength(_r_in, self.dispersion, _rng);
+        let scattered = |drc: Vec3, media: MediumStack, w: Vec3| {
+            let mut sed = Ray::new(rec.p, drc, _r_in.tm);
+            sed.media = Some(media);
+            sed.wavelength = wavelength;
+            match fresnel {
+                Some(_) => Scatter::new_spectral(Vec3::elemul(weight, w), sed),
+                None => Scatter::new(weight, sed),
+            }
         };
 
+        let (inner, ior) = cross_interface(_r_in, rec, self.entry(), wavelength);
+        let (n1, n2) = match ior {
+            Some(n) => n,
+            None => return Some(scattered(_r_in.drc, inner, Vec3::ones())),
+        };
+        let eoe = n1 / n2;
+
         let unit_drc: Vec3 = _r_in.drc.unit();
         let cos = -unit_drc * rec.n;
         let cos_theta: f64 = if cos < 1.0 { cos } else { 1.0 };
@@ -150,27 +344,43 @@ impl Material for Dielectric {
         if eoe * sin_theta > 1.0 {
             // must reflect
             let reflected: Vec3 = reflect(unit_drc, rec.n);
-            let sed = Ray::new(rec.p, reflected, _r_in.tm);
-            let rt = Scatter::new(att, sed);
-            return Some(rt);
+            return Some(scattered(reflected, stack, Vec3::ones()));
         }
 
-        let reflect_prob: f64 = schlick(cos_theta, eoe);
+        // 按平均反射率选择反射或折射，权重为各自的反射率/透射率除以选择概率
+        let f = match fresnel {
+            Some(f) => f(cos_theta, n1, Vec3::ones() * n2, Vec3::zero()),
+            None => Vec3::ones() * schlick(cos_theta, eoe),
+        };
+        let reflect_prob = (f.x + f.y + f.z) / 3.0;
         let flag: f64 = rand::thread_rng().gen();
         if flag < reflect_prob {
             let reflected: Vec3 = reflect(unit_drc, rec.n);
-            let sed = Ray::new(rec.p, reflected, _r_in.tm);
-            let rt = Scatter::new(att, sed);
-            return Some(rt);
+            return Some(scattered(reflected, stack, f / reflect_prob));
         }
 
         let refracted = refract(unit_drc, rec.n, eoe);
-        let sed = Ray::new(rec.p, refracted, _r_in.tm);
-        let rt = Scatter::new(att, sed);
-        Some(rt)
+        let w = (Vec3::ones() - f) / (1.0 - reflect_prob);
+        Some(scattered(refracted, inner, w))
     }
+}
 
-    fn emitted(&self, _u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+impl Material for Dielectric {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, None, _rng)
+    }
+
+    fn scatter_with_fresnel(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: FresnelFn,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, Some(fresnel), _rng)
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
         Vec3 {
             x: 0.0,
             y: 0.0,
@@ -181,12 +391,44 @@ impl Material for Dielectric {
 
 #[derive(Clone)]
 pub struct Diffuse {
+    // 发光材质
     pub emit: Arc<dyn Texture>,
+    pub strength: f64,
+    pub two_sided: bool,              // 为 false 时只在法线（front_face）一侧发光
+    pub visible: bool,                // 为 false 时对相机光线不可见，但仍照亮场景
+    pub spectrum: Option<Illuminant>, // 光谱模式下使用精确的光源光谱
 }
 
 impl Diffuse {
     pub fn new(emit: Arc<dyn Texture>) -> Diffuse {
-        Diffuse { emit }
+        Diffuse::new_with_strength(emit, 1.0)
+    }
+
+    pub fn new_with_strength(emit: Arc<dyn Texture>, strength: f64) -> Diffuse {
+        Diffuse {
+            emit,
+            strength,
+            two_sided: true,
+            visible: true,
+            spectrum: None,
+        }
+    }
+
+    // 按色温（开尔文）发光，颜色亮度归一化为 1
+    #[allow(dead_code)]
+    pub fn blackbody(kelvin: f64, strength: f64) -> Diffuse {
+        let mut d =
+            Diffuse::new_with_strength(Arc::new(Solid::new(blackbody_rgb(kelvin))), strength);
+        d.spectrum = Some(Illuminant::Blackbody(Blackbody::new(kelvin)));
+        d
+    }
+
+    // 标准光源 D65：RGB 模式下为白色，光谱模式下发出 D65 光谱
+    #[allow(dead_code)]
+    pub fn d65(strength: f64) -> Diffuse {
+        let mut d = Diffuse::new_with_strength(Arc::new(Solid::new(Vec3::ones())), strength);
+        d.spectrum = Some(Illuminant::D65);
+        d
     }
 }
 
@@ -195,8 +437,22 @@ impl Material for Diffuse {
         None
     }
 
-    fn emitted(&self, u: f64, v: f64, p: &Vec3) -> Vec3 {
-        self.emit.value(u, v, *p)
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        if !self.two_sided && !rec.front_face {
+            return Vec3::zero();
+        }
+        self.emit.value_at(rec) * self.strength
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        match self.spectrum {
+            Some(spd) if self.two_sided || rec.front_face => spd.sample(lambdas) * self.strength,
+            _ => upsample_illuminant(self.emitted(rec), lambdas),
+        }
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.visible
     }
 }
 
@@ -214,11 +470,283 @@ impl Isotropic {
 impl Material for Isotropic {
     fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
         let sed = Ray::new(rec.p, random_in_unit_sphere(_rng), _r_in.tm);
-        let att = self.albedo.value(rec.u, rec.v, rec.p);
-        Some(Scatter { ray: sed, att })
+        let att = self.albedo.value_at(rec);
+        Some(Scatter::new(att, sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+}
+
+// Henyey-Greenstein 采样：返回散射方向与入射方向夹角的余弦，g > 0 为前向散射
+pub fn sample_hg_cos(g: f64, xi: f64) -> f64 {
+    if g.abs() < 1e-3 {
+        return 1.0 - 2.0 * xi;
+    }
+    let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+    clamp((1.0 + g * g - s * s) / (2.0 * g), -1.0, 1.0)
+}
+
+pub fn hg_direction(drc: Vec3, cos_theta: f64, rng: &mut ThreadRng) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let uvw = Onb::build_from_w(drc);
+    uvw.local(Vec3::new(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
+
+#[derive(Clone)]
+pub struct HenyeyGreenstein {
+    // 各向异性相函数，g ∈ (-1, 1)
+    pub albedo: Arc<dyn Texture>,
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    #[allow(dead_code)]
+    pub fn new(albedo: Arc<dyn Texture>, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein { albedo, g }
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let cos_theta = sample_hg_cos(self.g, _rng.gen::<f64>());
+        let drc = hg_direction(_r_in.drc, cos_theta, _rng);
+        let sed = Ray::new(rec.p, drc, _r_in.tm);
+        let att = self.albedo.value_at(rec);
+        Some(Scatter::new(att, sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DoubleHenyeyGreenstein {
+    // 前向 g1 与后向 g2 两个波瓣按权重 w : (1 - w) 混合
+    pub albedo: Arc<dyn Texture>,
+    pub g1: f64,
+    pub g2: f64,
+    pub w: f64,
+}
+
+impl DoubleHenyeyGreenstein {
+    #[allow(dead_code)]
+    pub fn new(albedo: Arc<dyn Texture>, g1: f64, g2: f64, w: f64) -> DoubleHenyeyGreenstein {
+        DoubleHenyeyGreenstein { albedo, g1, g2, w }
+    }
+}
+
+impl Material for DoubleHenyeyGreenstein {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        // 按权重选择波瓣，采样的 pdf 正好等于混合后的相函数
+        let g = if _rng.gen::<f64>() < self.w {
+            self.g1
+        } else {
+            self.g2
+        };
+        let cos_theta = sample_hg_cos(g, _rng.gen::<f64>());
+        let drc = hg_direction(_r_in.drc, cos_theta, _rng);
+        let sed = Ray::new(rec.p, drc, _r_in.tm);
+        let att = self.albedo.value_at(rec);
+        Some(Scatter::new(att, sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum ConductorFresnel {
+    Complex { eta: Vec3, k: Vec3 }, // 复折射率
+    Schlick(Vec3),                  // 由颜色给出的正入射反射率
+}
+
+#[derive(Copy, Clone)]
+pub struct Conductor {
+    // GGX 微表面导体，取代用 fuzz 模拟粗糙度的 Metal
+    // 微表面间多次反射损失的能量按 Kulla-Conty 补偿，修改 ggx 后需重新构造
+    pub fresnel: ConductorFresnel,
+    pub ggx: Ggx,
+    albedo: GgxAlbedo,
+    fresnel_avg: Vec3,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: f64, anisotropy: f64) -> Conductor {
+        let (ax, ay) = anisotropic_alpha(roughness, anisotropy);
+        Conductor::with_ggx(ConductorFresnel::Complex { eta, k }, Ggx::new(ax, ay))
+    }
+
+    pub fn from_reflectance(f0: Vec3, roughness: f64) -> Conductor {
+        let alpha = roughness_to_alpha(roughness);
+        Conductor::with_ggx(ConductorFresnel::Schlick(f0), Ggx::isotropic(alpha))
     }
 
-    fn emitted(&self, _u: f64, _v: f64, _p: &Vec3) -> Vec3 {
+    fn with_ggx(fresnel: ConductorFresnel, ggx: Ggx) -> Conductor {
+        let mut c = Conductor {
+            fresnel,
+            ggx,
+            albedo: GgxAlbedo::new(&ggx),
+            fresnel_avg: Vec3::zero(),
+        };
+        c.fresnel_avg = Vec3::new(
+            cosine_average(|mu| c.fresnel(mu).x),
+            cosine_average(|mu| c.fresnel(mu).y),
+            cosine_average(|mu| c.fresnel(mu).z),
+        );
+        c
+    }
+
+    // 多次散射项的颜色：F_avg² E_avg / (1 - F_avg (1 - E_avg))，F = 1 时为 1
+    fn multiple_scattering_color(&self) -> Vec3 {
+        let e_avg = self.albedo.e_avg;
+        let f = |f_avg: f64| f_avg * f_avg * e_avg / (1.0 - f_avg * (1.0 - e_avg));
+        Vec3::new(
+            f(self.fresnel_avg.x),
+            f(self.fresnel_avg.y),
+            f(self.fresnel_avg.z),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn gold(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.143, 0.374, 1.442),
+            Vec3::new(3.983, 2.385, 1.603),
+            roughness,
+            0.0,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn silver(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.155, 0.117, 0.138),
+            Vec3::new(4.828, 3.122, 2.147),
+            roughness,
+            0.0,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn copper(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.200, 0.924, 1.102),
+            Vec3::new(3.912, 2.452, 2.142),
+            roughness,
+            0.0,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn aluminum(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(1.657, 0.880, 0.521),
+            Vec3::new(9.224, 6.270, 4.837),
+            roughness,
+            0.0,
+        )
+    }
+
+    pub fn fresnel(&self, cos_i: f64) -> Vec3 {
+        match self.fresnel {
+            ConductorFresnel::Complex { eta, k } => fresnel_conductor(cos_i, eta, k),
+            ConductorFresnel::Schlick(f0) => fresnel_schlick(cos_i, f0),
+        }
+    }
+
+    // 复折射率；由颜色给出时取 eta = 1 近似
+    fn optical_constants(&self) -> (Vec3, Vec3) {
+        match self.fresnel {
+            ConductorFresnel::Complex { eta, k } => (eta, k),
+            ConductorFresnel::Schlick(f0) => (Vec3::ones(), conductor_k(f0)),
+        }
+    }
+
+    // fresnel 为 None 时用自身的菲涅尔项，多次散射补偿项的颜色总是由自身的菲涅尔项给出
+    fn scatter_interface(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: Option<FresnelFn>,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+        let wo = uvw.world_to_local(-_r_in.drc.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        // 单次散射丢失的能量 1 - E(μo) 由补偿项
+        // f_ms = (1 - E(μo)) (1 - E(μi)) / (π (1 - E_avg)) 给出，按这一比例选择补偿项
+        let e_o = self.albedo.value(wo.z);
+        let q = if e_o < 1.0 - 1e-4 { 1.0 - e_o } else { 0.0 };
+        if _rng.gen::<f64>() < q {
+            // 余弦采样，权重 f_ms * π / q
+            let wi = uvw.world_to_local((rec.n + random_unit_vector(_rng)).unit());
+            if wi.z <= 0.0 {
+                return None;
+            }
+            let w = (1.0 - self.albedo.value(wi.z)) / (1.0 - self.albedo.e_avg);
+            let sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+            return Some(Scatter::new(self.multiple_scattering_color() * w, sed));
+        }
+
+        // 按可见法线采样，权重 f * cos / pdf = F * G2 / G1，再除以选择概率
+        let m = self
+            .ggx
+            .sample_vndf(wo, _rng.gen::<f64>(), _rng.gen::<f64>());
+        let wi = reflect_about(wo, m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let g = self.ggx.g2(wo, wi) / self.ggx.g1(wo) / (1.0 - q);
+        let sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+        match fresnel {
+            Some(f) => {
+                let (eta, k) = self.optical_constants();
+                Some(Scatter::new_spectral(f(wo * m, 1.0, eta, k) * g, sed))
+            }
+            None => Some(Scatter::new(self.fresnel(wo * m) * g, sed)),
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, None, _rng)
+    }
+
+    fn scatter_with_fresnel(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: FresnelFn,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, Some(fresnel), _rng)
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
         Vec3 {
             x: 0.0,
             y: 0.0,
@@ -226,3 +754,1153 @@ impl Material for Isotropic {
         }
     }
 }
+
+// 以微表面法线 m 折射，eta = 透射侧 / 入射侧折射率；全反射时返回 None
+pub fn refract_about(wo: Vec3, m: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo * m;
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wo / eta + m * (cos_i / eta - cos_t))
+}
+
+#[derive(Copy, Clone)]
+pub struct RoughDielectric {
+    // 磨砂玻璃：GGX 微表面反射与透射（Walter et al. 2007）
+    pub ref_idx: f64,
+    pub ggx: Ggx,
+    pub absorption: Vec3, // 内部每单位距离的吸收系数，由积分器按路径长度衰减
+    pub dispersion: Option<Dispersion>, // 与 Dielectric 相同，首次折射时采样波长
+    pub priority: i32,    // 嵌套时优先级高的介质占据重叠区域
+    id: usize,
+}
+
+impl RoughDielectric {
+    pub fn new(ref_idx: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            ref_idx,
+            ggx: Ggx::isotropic(roughness_to_alpha(roughness)),
+            absorption: Vec3::zero(),
+            dispersion: None,
+            priority: 0,
+            id: next_medium_id(),
+        }
+    }
+
+    // 磨砂的棱镜、水晶：RGB 光线使用 d 线处的折射率
+    #[allow(dead_code)]
+    pub fn new_dispersive(dispersion: Dispersion, roughness: f64) -> RoughDielectric {
+        let mut d = RoughDielectric::new(dispersion.ior(587.6), roughness);
+        d.dispersion = Some(dispersion);
+        d
+    }
+
+    fn entry(&self) -> MediumEntry {
+        MediumEntry {
+            id: self.id,
+            priority: self.priority,
+            ior: self.ref_idx,
+            dispersion: self.dispersion,
+            absorption: self.absorption,
+        }
+    }
+}
+
+impl RoughDielectric {
+    // fresnel 为 None 时用精确的介质菲涅尔项
+    fn scatter_interface(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: Option<FresnelFn>,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        let (wavelength, weight) = dispersion_wavelength(_r_in, self.dispersion, _rng);
+        let (inner, ior) = cross_interface(_r_in, rec, self.entry(), wavelength);
+        let (n1, n2) = match ior {
+            Some(n) => n,
+            None => {
+                return Some(Scatter::new(
+                    weight,
+                    pass_through(_r_in, rec, inner, wavelength),
+                ))
+            }
+        };
+        let eta = n2 / n1;
+        let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+        let wo = uvw.world_to_local(-_r_in.drc.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        // 以菲涅尔项的概率选择反射或透射，两种情况的权重都化简为 G2 / G1
+        let (wi, w) = match fresnel {
+            Some(f) => {
+                let film = |cos: f64| f(cos, n1, Vec3::ones() * n2, Vec3::zero());
+                sample_glass_with(&self.ggx, wo, eta, &film, _rng)?
+            }
+            None => (sample_glass(&self.ggx, wo, eta, _rng)?, Vec3::ones()),
+        };
+        let att = Vec3::elemul(weight, w) * (self.ggx.g2(wo, wi) / self.ggx.g1(wo));
+        let mut sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+        sed.wavelength = wavelength;
+        if wi.z < 0.0 {
+            sed.media = Some(inner);
+        }
+        match fresnel {
+            Some(_) => Some(Scatter::new_spectral(att, sed)),
+            None => Some(Scatter::new(att, sed)),
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, None, _rng)
+    }
+
+    fn scatter_with_fresnel(
+        &self,
+        _r_in: &Ray,
+        rec: &Hitrecord,
+        fresnel: FresnelFn,
+        _rng: &mut ThreadRng,
+    ) -> Option<Scatter> {
+        self.scatter_interface(_r_in, rec, Some(fresnel), _rng)
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+}
+
+pub fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - clamp(cos, 0.0, 1.0)).powi(5)
+}
+
+// 清漆层固定为较光滑的 GGX，F0 = 0.04（折射率 1.5）
+const CLEARCOAT_ALPHA: f64 = 0.05;
+const CLEARCOAT_F0: f64 = 0.04;
+
+#[derive(Clone)]
+pub struct Principled {
+    // Disney 风格的统一材质，各参数均由纹理给出；标量参数取纹理的 x 分量
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    pub specular: Arc<dyn Texture>, // 0.5 对应折射率 1.5
+    pub specular_tint: Arc<dyn Texture>,
+    pub sheen: Arc<dyn Texture>,
+    pub clearcoat: Arc<dyn Texture>,
+    pub transmission: Arc<dyn Texture>,
+    pub emission: Arc<dyn Texture>,
+    pub dispersion: Option<Dispersion>, // 设置后透射层按波长取折射率，代替由 specular 反推的值
+    pub priority: i32,                  // 透射层嵌套时的优先级，与 Dielectric 相同
+    id: usize,                          // 透射层在介质栈中的标识
+}
+
+// 某一交点处求值后的参数
+struct PrincipledParams {
+    base: Vec3,
+    tint: Vec3,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    clearcoat: f64,
+    transmission: f64,
+}
+
+impl Principled {
+    #[allow(dead_code)]
+    pub fn new(base_color: Arc<dyn Texture>) -> Principled {
+        let constant = |x: f64| -> Arc<dyn Texture> { Arc::new(Solid::new(Vec3::ones() * x)) };
+        Principled {
+            base_color,
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            specular_tint: constant(0.0),
+            sheen: constant(0.0),
+            clearcoat: constant(0.0),
+            transmission: constant(0.0),
+            emission: constant(0.0),
+            dispersion: None,
+            priority: 0,
+            id: next_medium_id(),
+        }
+    }
+
+    // 光谱模式下底色与色调上采样到光线的波长，之后的组合都逐波长进行
+    fn params(&self, rec: &Hitrecord, r_in: &Ray) -> PrincipledParams {
+        let scalar = |t: &Arc<dyn Texture>| clamp(t.value_at(rec).x, 0.0, 1.0);
+        let base = self.base_color.value_at(rec);
+        let lum = luminance(base);
+        let tint = if lum > 0.0 { base / lum } else { Vec3::ones() };
+        PrincipledParams {
+            base: spectral_reflectance(base, r_in),
+            tint: spectral_reflectance(tint, r_in),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            specular_tint: scalar(&self.specular_tint),
+            sheen: scalar(&self.sheen),
+            clearcoat: scalar(&self.clearcoat),
+            transmission: scalar(&self.transmission),
+        }
+    }
+
+    // 由 specular 反推的折射率，供透射层使用
+    fn ior(specular: f64) -> f64 {
+        let r = (0.08 * specular).sqrt().min(0.99);
+        (1.0 + r) / (1.0 - r)
+    }
+
+    fn entry(&self, ior: f64) -> MediumEntry {
+        MediumEntry {
+            id: self.id,
+            priority: self.priority,
+            ior,
+            dispersion: self.dispersion,
+            absorption: Vec3::zero(),
+        }
+    }
+
+    // 透射层：色散时采样波长，再由介质栈给出折射率之比（透射侧 / 入射侧）与透射后的介质栈，
+    // 返回波长、RGB 权重、介质栈与折射率之比
+    fn glass_interface(
+        &self,
+        specular: f64,
+        r_in: &Ray,
+        rec: &Hitrecord,
+        rng: &mut ThreadRng,
+    ) -> (Option<f64>, Vec3, MediumStack, f64) {
+        let (wavelength, weight) = dispersion_wavelength(r_in, self.dispersion, rng);
+        let entry = self.entry(Principled::ior(specular));
+        let (inner, ior) = cross_interface(r_in, rec, entry, wavelength);
+        let eta = ior.map_or(1.0, |(n1, n2)| n2 / n1);
+        (wavelength, weight, inner, eta)
+    }
+}
+
+impl PrincipledParams {
+    fn spec_f0(&self) -> Vec3 {
+        let dielectric = lerp(Vec3::ones(), self.tint, self.specular_tint) * (0.08 * self.specular);
+        lerp(dielectric, self.base, self.metallic)
+    }
+
+    // 漫反射层：Disney 漫反射（含逆反射）加上 sheen，按余弦采样时权重为 f * PI
+    fn diffuse(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        let h = (wo + wi).unit();
+        let cos_d = wi * h;
+        let fl = schlick_weight(wi.z);
+        let fv = schlick_weight(wo.z);
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+        let sheen = lerp(Vec3::ones(), self.tint, 0.5) * (self.sheen * schlick_weight(cos_d));
+        (self.base * fd + sheen) * ((1.0 - self.metallic) * (1.0 - self.transmission))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let s = self.params(rec, _r_in);
+        let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+        let wo = uvw.world_to_local(-_r_in.drc.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let ggx = Ggx::isotropic(roughness_to_alpha(s.roughness));
+        let glass = (1.0 - s.metallic) * s.transmission;
+
+        // 透射物体处在更高优先级的介质中时，整个界面视为不存在
+        if glass > 0.0 {
+            let entry = self.entry(Principled::ior(s.specular));
+            if let (inner, None) = cross_interface(_r_in, rec, entry, _r_in.wavelength) {
+                let sed = pass_through(_r_in, rec, inner, _r_in.wavelength);
+                return Some(Scatter::new(Vec3::ones(), sed));
+            }
+        }
+
+        // 从内部射出的光线只经过透射层
+        if !rec.front_face && glass > 0.0 {
+            let (wavelength, weight, inner, eta) =
+                self.glass_interface(s.specular, _r_in, rec, _rng);
+            let wi = sample_glass(&ggx, wo, eta, _rng)?;
+            let att = weight * (ggx.g2(wo, wi) / ggx.g1(wo));
+            let mut sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+            sed.wavelength = wavelength;
+            if wi.z < 0.0 {
+                sed.media = Some(inner);
+            }
+            return Some(Scatter::new_spectral(att, sed));
+        }
+
+        // 按各层的近似反照率选择一层采样，权重除以选择概率
+        let f0 = s.spec_f0();
+        let spec_scale = 1.0 - glass;
+        let coat_f = fresnel_schlick(wo.z, Vec3::ones() * CLEARCOAT_F0).x;
+        let weights = [
+            (1.0 - s.metallic) * (1.0 - s.transmission) * (luminance(s.base) + s.sheen),
+            spec_scale * luminance(fresnel_schlick(wo.z, f0)),
+            0.25 * s.clearcoat * coat_f,
+            glass,
+        ];
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut xi = _rng.gen::<f64>() * total;
+        let mut lobe = 0;
+        while lobe < 3 && xi >= weights[lobe] {
+            xi -= weights[lobe];
+            lobe += 1;
+        }
+        let prob = weights[lobe] / total;
+
+        // 只有透射层按波长折射并改变介质栈，其余层沿用入射光线的波长与介质
+        let mut wavelength = _r_in.wavelength;
+        let mut media = None;
+        let (wi, f) = match lobe {
+            0 => {
+                let wi = uvw.world_to_local((rec.n + random_unit_vector(_rng)).unit());
+                if wi.z <= 0.0 {
+                    return None;
+                }
+                (wi, s.diffuse(wo, wi) * (1.0 / prob))
+            }
+            1 => {
+                let m = ggx.sample_vndf(wo, _rng.gen::<f64>(), _rng.gen::<f64>());
+                let wi = reflect_about(wo, m);
+                if wi.z <= 0.0 {
+                    return None;
+                }
+                let w = spec_scale * ggx.g2(wo, wi) / ggx.g1(wo) / prob;
+                (wi, fresnel_schlick(wo * m, f0) * w)
+            }
+            2 => {
+                let coat = Ggx::isotropic(CLEARCOAT_ALPHA);
+                let m = coat.sample_vndf(wo, _rng.gen::<f64>(), _rng.gen::<f64>());
+                let wi = reflect_about(wo, m);
+                if wi.z <= 0.0 {
+                    return None;
+                }
+                let w = 0.25 * s.clearcoat * coat.g2(wo, wi) / coat.g1(wo) / prob;
+                (wi, fresnel_schlick(wo * m, Vec3::ones() * CLEARCOAT_F0) * w)
+            }
+            _ => {
+                let (lambda, weight, inner, eta) =
+                    self.glass_interface(s.specular, _r_in, rec, _rng);
+                let wi = sample_glass(&ggx, wo, eta, _rng)?;
+                wavelength = lambda;
+                let w = glass * ggx.g2(wo, wi) / ggx.g1(wo) / prob;
+                // 进入物体时透射光带上底色
+                let tint = if wi.z < 0.0 {
+                    media = Some(inner);
+                    s.base
+                } else {
+                    Vec3::ones()
+                };
+                (wi, Vec3::elemul(tint, weight) * w)
+            }
+        };
+        let mut sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+        sed.wavelength = wavelength;
+        sed.media = media;
+        Some(Scatter::new_spectral(f, sed))
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.emission.value_at(rec)
+    }
+}
+
+// 按可见法线采样微表面，再以菲涅尔项的概率选择反射或透射，返回局部坐标下的出射方向
+fn sample_glass(ggx: &Ggx, wo: Vec3, eta: f64, rng: &mut ThreadRng) -> Option<Vec3> {
+    let f = |cos: f64| Vec3::ones() * fresnel_dielectric(cos, eta);
+    sample_glass_with(ggx, wo, eta, &f, rng).map(|(wi, _)| wi)
+}
+
+// 同上，反射率由 fresnel 给出（按微表面上的入射角余弦），返回出射方向与反射/透射的权重
+fn sample_glass_with(
+    ggx: &Ggx,
+    wo: Vec3,
+    eta: f64,
+    fresnel: &dyn Fn(f64) -> Vec3,
+    rng: &mut ThreadRng,
+) -> Option<(Vec3, Vec3)> {
+    let m = ggx.sample_vndf(wo, rng.gen::<f64>(), rng.gen::<f64>());
+    let f = fresnel(wo * m);
+    let p = (f.x + f.y + f.z) / 3.0;
+    if rng.gen::<f64>() < p {
+        let wi = reflect_about(wo, m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some((wi, f / p))
+    } else {
+        let wi = refract_about(wo, m, eta)?;
+        if wi.z >= 0.0 {
+            return None;
+        }
+        Some((wi, (Vec3::ones() - f) / (1.0 - p)))
+    }
+}
+
+#[derive(Clone)]
+pub struct MixMaterial {
+    // 按权重随机选择两种材质之一，weight 为 b 的比例（取纹理的 x 分量）
+    pub a: Arc<dyn Material>,
+    pub b: Arc<dyn Material>,
+    pub weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(
+        a: Arc<dyn Material>,
+        b: Arc<dyn Material>,
+        weight: Arc<dyn Texture>,
+    ) -> MixMaterial {
+        MixMaterial { a, b, weight }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_ratio(a: Arc<dyn Material>, b: Arc<dyn Material>, ratio: f64) -> MixMaterial {
+        MixMaterial::new(a, b, Arc::new(Solid::new(Vec3::ones() * ratio)))
+    }
+
+    fn ratio(&self, u: f64, v: f64, p: &Vec3) -> f64 {
+        clamp(self.weight.value(u, v, *p).x, 0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        if _rng.gen::<f64>() < self.ratio(rec.u, rec.v, &rec.p) {
+            self.b.scatter(_r_in, rec, _rng)
+        } else {
+            self.a.scatter(_r_in, rec, _rng)
+        }
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        let t = self.ratio(rec.u, rec.v, &rec.p);
+        lerp(self.a.emitted(rec), self.b.emitted(rec), t)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        let t = self.ratio(rec.u, rec.v, &rec.p);
+        lerp(
+            self.a.emitted_spectrum(rec, lambdas),
+            self.b.emitted_spectrum(rec, lambdas),
+            t,
+        )
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        let t = self.ratio(rec.u, rec.v, &rec.p);
+        self.a.opacity(rec) * (1.0 - t) + self.b.opacity(rec) * t
+    }
+
+    // 任一分量可见即视为可见
+    fn visible_to_camera(&self) -> bool {
+        self.a.visible_to_camera() || self.b.visible_to_camera()
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        self.a.is_shadow_catcher() || self.b.is_shadow_catcher()
+    }
+}
+
+#[derive(Clone)]
+pub struct Coated {
+    // 光滑透明涂层（清漆）覆盖在任意底层材质上，层内可带吸收
+    pub base: Arc<dyn Material>,
+    pub ref_idx: f64,
+    pub absorption: Vec3, // 涂层内的吸收系数乘以厚度，垂直穿过一次衰减为 exp(-absorption)
+    pub max_bounces: usize,
+}
+
+impl Coated {
+    #[allow(dead_code)]
+    pub fn new(base: Arc<dyn Material>, ref_idx: f64) -> Coated {
+        Coated::new_tinted(base, ref_idx, Vec3::zero())
+    }
+
+    pub fn new_tinted(base: Arc<dyn Material>, ref_idx: f64, absorption: Vec3) -> Coated {
+        Coated {
+            base,
+            ref_idx,
+            absorption,
+            max_bounces: 16,
+        }
+    }
+
+    // 垂直穿过的透射率按反射率上采样，斜穿时按路径长度取幂
+    fn transmittance(&self, cos: f64, r_in: &Ray) -> Vec3 {
+        let d = -self.absorption;
+        let t = spectral_reflectance(Vec3::new(d.x.exp(), d.y.exp(), d.z.exp()), r_in);
+        let k = 1.0 / cos.abs().max(1e-3);
+        Vec3::new(
+            t.x.max(0.0).powf(k),
+            t.y.max(0.0).powf(k),
+            t.z.max(0.0).powf(k),
+        )
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let unit = _r_in.drc.unit();
+        let cos_i = -(unit * rec.n);
+
+        // 涂层表面：按菲涅尔项镜面反射，否则折射进入涂层
+        if _rng.gen::<f64>() < fresnel_dielectric(cos_i, self.ref_idx) {
+            let sed = Ray::new(rec.p, reflect(unit, rec.n), _r_in.tm);
+            return Some(Scatter::new(Vec3::ones(), sed));
+        }
+
+        // 涂层视为无限薄：在同一点与底层交互，光线在两层之间反复反弹直到离开
+        let mut drc = refract(unit, rec.n, 1.0 / self.ref_idx);
+        let mut att = self.transmittance(drc * rec.n, _r_in);
+        for _ in 0..self.max_bounces {
+            let mut inner = Ray::new(rec.p, drc, _r_in.tm);
+            inner.spectral = _r_in.spectral;
+            inner.wavelength = _r_in.wavelength;
+            let s = self.base.scatter(&inner, rec, _rng)?;
+            let up = s.ray.drc.unit();
+            let cos_o = up * rec.n;
+            // 底层的 RGB 衰减先上采样，再与涂层的透射率逐波长相乘
+            let base = if s.spectral {
+                s.att
+            } else {
+                spectral_reflectance(s.att, _r_in)
+            };
+            att = Vec3::elemul(att, base);
+            if cos_o <= 0.0 {
+                // 底层透射：光线穿过涂层和底层进入物体内部，介质栈等由底层维护
+                return Some(Scatter::new_spectral(att, s.ray));
+            }
+            att = Vec3::elemul(att, self.transmittance(cos_o, _r_in));
+            if _rng.gen::<f64>() < fresnel_dielectric(cos_o, 1.0 / self.ref_idx) {
+                drc = reflect(up, rec.n);
+                att = Vec3::elemul(att, self.transmittance(cos_o, _r_in));
+            } else {
+                let out = refract(up, -rec.n, self.ref_idx);
+                return Some(Scatter::new_spectral(att, Ray::new(rec.p, out, _r_in.tm)));
+            }
+        }
+        None
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.base.emitted(rec)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        self.base.emitted_spectrum(rec, lambdas)
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        self.base.opacity(rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.base.visible_to_camera()
+    }
+}
+
+// 用切线空间中的法线替换着色法线，切线取 dp/du 方向，副切线与 dp/dv 同向
+fn perturb_normal(rec: &Hitrecord, r_in: &Ray, local: Vec3) -> Hitrecord {
+    let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+    let sign = if uvw.v * rec.dpdv < 0.0 { -1.0 } else { 1.0 };
+    let n = uvw
+        .local(Vec3::new(local.x, sign * local.y, local.z))
+        .unit();
+    let mut shading = rec.clone();
+    // 扰动后的法线不能背向入射光线
+    if n * r_in.drc < 0.0 {
+        shading.n = n;
+        shading.dpdu = uvw.u;
+    }
+    shading
+}
+
+#[derive(Clone)]
+pub struct NormalMap {
+    // 切线空间法线贴图：颜色 (r, g, b) 映射到 (x, y, z) = 2 * c - 1
+    pub inner: Arc<dyn Material>,
+    pub map: Arc<dyn Texture>,
+    pub strength: f64,
+}
+
+impl NormalMap {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Material>, map: Arc<dyn Texture>) -> NormalMap {
+        NormalMap::new_with_strength(inner, map, 1.0)
+    }
+
+    pub fn new_with_strength(
+        inner: Arc<dyn Material>,
+        map: Arc<dyn Texture>,
+        strength: f64,
+    ) -> NormalMap {
+        NormalMap {
+            inner,
+            map,
+            strength,
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let c = self.map.value_at(rec) * 2.0 - 1.0;
+        let local = Vec3::new(c.x * self.strength, c.y * self.strength, c.z.max(1e-3));
+        let shading = perturb_normal(rec, _r_in, local);
+        self.inner.scatter(_r_in, &shading, _rng)
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.inner.emitted(rec)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        self.inner.emitted_spectrum(rec, lambdas)
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        self.inner.opacity(rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.inner.visible_to_camera()
+    }
+}
+
+#[derive(Clone)]
+pub struct BumpMap {
+    // 高度贴图：取纹理 x 分量为高度，按 uv 差分求梯度
+    pub inner: Arc<dyn Material>,
+    pub height: Arc<dyn Texture>,
+    pub scale: f64,
+    pub delta: f64, // 差分步长（uv 单位）
+}
+
+impl BumpMap {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> BumpMap {
+        BumpMap {
+            inner,
+            height,
+            scale,
+            delta: 1e-3,
+        }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        // 同时沿 dp/du、dp/dv 移动采样点，使立体纹理也能得到梯度
+        let d = self.delta;
+        let h0 = self.height.value(rec.u, rec.v, rec.p).x;
+        let hu = (self.height.value(rec.u + d, rec.v, rec.p + rec.dpdu * d).x - h0) / d;
+        let hv = (self.height.value(rec.u, rec.v + d, rec.p + rec.dpdv * d).x - h0) / d;
+        // 位移后的曲面 p' = p + scale * h * n 的偏导数为 dp/du + scale * dh/du * n（忽略法线的变化），
+        // 二者的叉积即扰动后的法线，梯度因此按 dp/du、dp/dv 的长度换算到世界空间
+        let (dpdu, dpdv) = if rec.dpdu.length() > 0.0 && rec.dpdv.length() > 0.0 {
+            (rec.dpdu, rec.dpdv)
+        } else {
+            let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+            (uvw.u, uvw.v)
+        };
+        let dpdu = dpdu + rec.n * (self.scale * hu);
+        let dpdv = dpdv + rec.n * (self.scale * hv);
+        let mut n = Vec3::cross(dpdu, dpdv).unit();
+        if n * rec.n < 0.0 {
+            n = -n;
+        }
+        let mut shading = rec.clone();
+        // 扰动后的法线不能背向入射光线
+        if n * _r_in.drc < 0.0 {
+            shading.n = n;
+            shading.dpdu = dpdu;
+            shading.dpdv = dpdv;
+        }
+        self.inner.scatter(_r_in, &shading, _rng)
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.inner.emitted(rec)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        self.inner.emitted_spectrum(rec, lambdas)
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        self.inner.opacity(rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.inner.visible_to_camera()
+    }
+}
+
+// 余弦加权采样局部半球方向
+fn cosine_direction(rng: &mut ThreadRng) -> Vec3 {
+    let r1 = rng.gen::<f64>();
+    let r2 = rng.gen::<f64>();
+    let phi = 2.0 * PI * r1;
+    let r = r2.sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - r2).max(0.0).sqrt())
+}
+
+#[derive(Clone)]
+pub struct OrenNayar {
+    // 粗糙漫反射（黏土、混凝土、月面），sigma 为微表面坡度的标准差（弧度），取纹理的 x 分量
+    pub albedo: Arc<dyn Texture>,
+    pub sigma: Arc<dyn Texture>,
+}
+
+impl OrenNayar {
+    pub fn new(albedo: Arc<dyn Texture>, sigma: Arc<dyn Texture>) -> OrenNayar {
+        OrenNayar { albedo, sigma }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_sigma(albedo: Arc<dyn Texture>, sigma: f64) -> OrenNayar {
+        OrenNayar::new(albedo, Arc::new(Solid::new(Vec3::ones() * sigma)))
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+        let wo = uvw.world_to_local(-_r_in.drc.unit());
+        let wi = cosine_direction(_rng);
+
+        let s2 = self.sigma.value_at(rec).x.powi(2);
+        let a = 1.0 - 0.5 * s2 / (s2 + 0.33);
+        let b = 0.45 * s2 / (s2 + 0.09);
+
+        let cos_o = wo.z.abs().min(1.0);
+        let sin_o = (1.0 - cos_o * cos_o).sqrt();
+        let sin_i = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+        let mut cos_dphi = 0.0;
+        if sin_o > 1e-4 && sin_i > 1e-4 {
+            cos_dphi = ((wi.x * wo.x + wi.y * wo.y) / (sin_i * sin_o)).max(0.0);
+        }
+        // sin(alpha) * tan(beta)，alpha、beta 分别为两天顶角中较大、较小者
+        let (sin_alpha, tan_beta) = if wi.z > cos_o {
+            (sin_o, sin_i / wi.z.max(1e-6))
+        } else {
+            (sin_i, sin_o / cos_o.max(1e-6))
+        };
+
+        // 余弦采样时权重为 f * PI
+        let att = self.albedo.value_at(rec) * (a + b * cos_dphi * sin_alpha * tan_beta);
+        let sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+        Some(Scatter::new(att, sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3::zero()
+    }
+}
+
+// Charlie 光泽分布（Estevez & Kulla 2017），cos_h 为半程向量与法线夹角的余弦
+fn charlie_d(alpha: f64, cos_h: f64) -> f64 {
+    let inv = 1.0 / alpha;
+    let sin2 = (1.0 - cos_h * cos_h).max(0.0);
+    (2.0 + inv) * sin2.powf(0.5 * inv) / (2.0 * PI)
+}
+
+#[derive(Clone)]
+pub struct Sheen {
+    // 天鹅绒、织物：漫反射底色加上掠射角处增强的 sheen 光泽
+    pub albedo: Arc<dyn Texture>,
+    pub sheen: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+}
+
+impl Sheen {
+    #[allow(dead_code)]
+    pub fn new(
+        albedo: Arc<dyn Texture>,
+        sheen: Arc<dyn Texture>,
+        roughness: Arc<dyn Texture>,
+    ) -> Sheen {
+        Sheen {
+            albedo,
+            sheen,
+            roughness,
+        }
+    }
+}
+
+impl Material for Sheen {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let uvw = Onb::build_from_wu(rec.n, rec.dpdu);
+        let wo = uvw.world_to_local(-_r_in.drc.unit());
+        let wi = cosine_direction(_rng);
+        let cos_o = wo.z.abs();
+
+        let alpha = roughness_to_alpha(clamp(self.roughness.value_at(rec).x, 0.07, 1.0));
+        let h = (Vec3::new(wo.x, wo.y, cos_o) + wi).unit();
+        // Ashikhmin 可见性项
+        let vis = 1.0 / (4.0 * (wi.z + cos_o - wi.z * cos_o)).max(1e-6);
+        let sheen = charlie_d(alpha, h.z) * vis * PI;
+
+        let att = spectral_reflectance(self.albedo.value_at(rec), _r_in)
+            + spectral_reflectance(self.sheen.value_at(rec), _r_in) * sheen;
+        let sed = Ray::new(rec.p, uvw.local(wi), _r_in.tm);
+        Some(Scatter::new_spectral(att, sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3::zero()
+    }
+}
+
+#[derive(Clone)]
+pub struct Cutout {
+    // 镂空：树叶、栅栏、贴花，mask 的 x 分量或 alpha 通道给出不透明度
+    pub inner: Arc<dyn Material>,
+    pub mask: Arc<dyn Texture>,
+    pub use_alpha: bool,
+}
+
+impl Cutout {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Material>, mask: Arc<dyn Texture>) -> Cutout {
+        Cutout {
+            inner,
+            mask,
+            use_alpha: false,
+        }
+    }
+
+    // 使用贴图自身的 alpha 通道（例如带透明度的 PNG）
+    #[allow(dead_code)]
+    pub fn from_alpha(inner: Arc<dyn Material>, mask: Arc<dyn Texture>) -> Cutout {
+        Cutout {
+            inner,
+            mask,
+            use_alpha: true,
+        }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        self.inner.scatter(_r_in, rec, _rng)
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.inner.emitted(rec)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        self.inner.emitted_spectrum(rec, lambdas)
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        let a = if self.use_alpha {
+            self.mask.alpha(rec.u, rec.v, rec.p)
+        } else {
+            self.mask.value_at(rec).x
+        };
+        clamp(a, 0.0, 1.0) * self.inner.opacity(rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.inner.visible_to_camera()
+    }
+}
+
+#[derive(Clone)]
+pub struct ShadowCatcher {
+    // 合成用的地面：对场景中其他物体表现为漫反射（albedo 取照片中地面的颜色），
+    // 透明输出时相机看到的只有它接收到的阴影
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl ShadowCatcher {
+    pub fn new(albedo: Arc<dyn Texture>) -> ShadowCatcher {
+        ShadowCatcher { albedo }
+    }
+}
+
+impl Material for ShadowCatcher {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let uvw = Onb::build_from_w(rec.n);
+        let sed = Ray::new(rec.p, uvw.local(cosine_direction(_rng)), _r_in.tm);
+        Some(Scatter::new(self.albedo.value_at(rec), sed))
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(mat: Arc<dyn Material>) -> Hitrecord {
+        Hitrecord::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0), 1.0, mat)
+    }
+
+    #[test]
+    fn test_wrapped_cutout_opacity() {
+        // 包在其他材质里的镂空仍然要让光线穿过
+        let white: Arc<dyn Texture> = Arc::new(Solid::new(Vec3::ones()));
+        let hole: Arc<dyn Material> = Arc::new(Cutout::new(
+            Arc::new(Lambertian::new(white.clone())),
+            Arc::new(Solid::new(Vec3::zero())),
+        ));
+        let solid: Arc<dyn Material> = Arc::new(Lambertian::new(white));
+        let coated: Arc<dyn Material> = Arc::new(Coated::new(hole.clone(), 1.5));
+        assert_eq!(coated.opacity(&record(coated.clone())), 0.0);
+        let mix: Arc<dyn Material> = Arc::new(MixMaterial::with_ratio(solid, hole, 0.25));
+        assert!((mix.opacity(&record(mix.clone())) - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_coated_glass_transmits() {
+        // 底层折射进入物体时穿过涂层继续传播
+        let coated: Arc<dyn Material> = Arc::new(Coated::new(Arc::new(Dielectric::new(1.5)), 1.5));
+        let rec = record(coated.clone());
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut rng = rand::thread_rng();
+        let transmitted = (0..64)
+            .filter_map(|_| coated.scatter(&down, &rec, &mut rng))
+            .filter(|s| s.ray.drc.z < 0.0)
+            .count();
+        assert!(transmitted > 0);
+    }
+
+    #[test]
+    fn test_mix_forwards_flags() {
+        let white: Arc<dyn Texture> = Arc::new(Solid::new(Vec3::ones()));
+        let catcher: Arc<dyn Material> = Arc::new(ShadowCatcher::new(white.clone()));
+        let solid: Arc<dyn Material> = Arc::new(Lambertian::new(white));
+        let mix = MixMaterial::with_ratio(solid.clone(), catcher, 0.5);
+        assert!(mix.is_shadow_catcher());
+        assert!(mix.visible_to_camera());
+        assert!(!MixMaterial::with_ratio(solid.clone(), solid, 0.5).is_shadow_catcher());
+    }
+
+    struct RampU;
+
+    impl Texture for RampU {
+        fn value(&self, u: f64, _v: f64, _p: Vec3) -> Vec3 {
+            Vec3::ones() * u
+        }
+    }
+
+    #[test]
+    fn test_bump_scales_by_dpdu() {
+        // h = u，dp/du 长度为 2：世界空间中的坡度为 1/2
+        let bump: Arc<dyn Material> = Arc::new(BumpMap::new(
+            Arc::new(Metal::new(Vec3::ones(), 0.0)),
+            Arc::new(RampU),
+            1.0,
+        ));
+        let mut rec = record(bump.clone());
+        rec.dpdu = Vec3::new(2.0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, 2.0, 0.0);
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let s = bump.scatter(&down, &rec, &mut rand::thread_rng()).unwrap();
+        let n = Vec3::new(-1.0, 0.0, 2.0).unit();
+        let expected = down.drc - n * (2.0 * (down.drc * n));
+        assert!((s.ray.drc.unit() - expected).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_rough_glass_medium_stack() {
+        // 折射进入时压栈，从背面离开时出栈
+        let mut glass = RoughDielectric::new(1.5, 0.0);
+        glass.absorption = Vec3::new(0.1, 0.2, 0.3);
+        let glass: Arc<dyn Material> = Arc::new(glass);
+        let mut rng = rand::thread_rng();
+        let mut rec = record(glass.clone());
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let inside = loop {
+            let s = glass.scatter(&down, &rec, &mut rng).unwrap();
+            if s.ray.drc.z < 0.0 {
+                break s.ray;
+            }
+        };
+        let top = inside.media.and_then(|m| m.top()).unwrap();
+        assert_eq!(top.absorption.z, 0.3);
+
+        rec.front_face = false;
+        let out = loop {
+            let s = glass.scatter(&inside, &rec, &mut rng).unwrap();
+            if s.ray.drc.z < 0.0 {
+                break s.ray;
+            }
+        };
+        assert!(out.media.unwrap().is_empty());
+    }
+
+    fn medium(ior: f64, priority: i32) -> MediumEntry {
+        MediumEntry {
+            id: next_medium_id(),
+            priority,
+            ior,
+            dispersion: None,
+            absorption: Vec3::zero(),
+        }
+    }
+
+    #[test]
+    fn test_rough_glass_nested() {
+        // 浸在同折射率液体中的磨砂玻璃不再折射
+        let rough = RoughDielectric::new(1.5, 0.0);
+        let id = rough.id;
+        let glass: Arc<dyn Material> = Arc::new(rough);
+        let rec = record(glass.clone());
+        let mut rng = rand::thread_rng();
+        let mut down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.3, 0.0, -1.0), 0.0);
+        let mut stack = MediumStack::new();
+        stack.push(medium(1.5, 0));
+        down.media = Some(stack);
+        let s = loop {
+            let s = glass.scatter(&down, &rec, &mut rng).unwrap();
+            if s.ray.drc.z < 0.0 {
+                break s;
+            }
+        };
+        assert!((s.ray.drc.unit() - down.drc.unit()).length() < 1e-6);
+
+        // 被更高优先级的介质覆盖时直接穿过，但仍然压栈
+        let water = medium(1.33, 1);
+        let mut stack = MediumStack::new();
+        stack.push(water);
+        down.media = Some(stack);
+        let s = glass.scatter(&down, &rec, &mut rng).unwrap();
+        assert!((s.ray.drc - down.drc).length() < 1e-12);
+        let media = s.ray.media.unwrap();
+        assert!(media.contains(water.id) && media.contains(id));
+    }
+
+    #[test]
+    fn test_principled_glass_nested() {
+        let mut principled = Principled::new(Arc::new(Solid::new(Vec3::ones())));
+        principled.transmission = Arc::new(Solid::new(Vec3::ones()));
+        principled.roughness = Arc::new(Solid::new(Vec3::zero()));
+        let id = principled.id;
+        let glass: Arc<dyn Material> = Arc::new(principled);
+        let rec = record(glass.clone());
+        let mut rng = rand::thread_rng();
+        let mut down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.3, 0.0, -1.0), 0.0);
+        let mut stack = MediumStack::new();
+        stack.push(medium(Principled::ior(0.5), 0));
+        down.media = Some(stack);
+        let s = loop {
+            let s = glass.scatter(&down, &rec, &mut rng).unwrap();
+            if s.ray.drc.z < 0.0 {
+                break s;
+            }
+        };
+        assert!((s.ray.drc.unit() - down.drc.unit()).length() < 1e-6);
+        assert!(s.ray.media.unwrap().contains(id));
+
+        let mut stack = MediumStack::new();
+        stack.push(medium(1.33, 1));
+        down.media = Some(stack);
+        let s = glass.scatter(&down, &rec, &mut rng).unwrap();
+        assert!((s.ray.drc - down.drc).length() < 1e-12);
+        assert!(s.ray.media.unwrap().contains(id));
+    }
+
+    #[test]
+    fn test_dispersive_glass_samples_wavelength() {
+        // 磨砂玻璃和 Principled 的透射层同样在首次折射时确定波长
+        let rough: Arc<dyn Material> =
+            Arc::new(RoughDielectric::new_dispersive(Dispersion::diamond(), 0.2));
+        let mut principled = Principled::new(Arc::new(Solid::new(Vec3::ones())));
+        principled.transmission = Arc::new(Solid::new(Vec3::ones()));
+        principled.dispersion = Some(Dispersion::diamond());
+        let principled: Arc<dyn Material> = Arc::new(principled);
+        let mut rng = rand::thread_rng();
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        for glass in [rough, principled].iter() {
+            let rec = record(glass.clone());
+            let s = loop {
+                if let Some(s) = glass.scatter(&down, &rec, &mut rng) {
+                    break s;
+                }
+            };
+            let lambda = s.ray.wavelength.unwrap();
+            assert!((380.0..=780.0).contains(&lambda));
+        }
+    }
+
+    #[test]
+    fn test_rough_conductor_white_furnace() {
+        // 白色导体在多次散射补偿后不损失能量
+        let metal: Arc<dyn Material> = Arc::new(Conductor::from_reflectance(Vec3::ones(), 1.0));
+        let rec = record(metal.clone());
+        let r = Ray::new(Vec3::zero(), Vec3::new(0.6, 0.0, -0.8), 0.0);
+        let mut rng = rand::thread_rng();
+        let n = 20000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            if let Some(s) = metal.scatter(&r, &rec, &mut rng) {
+                sum += s.att.x;
+            }
+        }
+        assert!((sum / n as f64 - 1.0).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_d65_emits_spd() {
+        let light: Arc<dyn Material> = Arc::new(Diffuse::d65(2.0));
+        let lambdas = Vec3::new(400.0, 550.0, 700.0);
+        let e = light.emitted_spectrum(&record(light.clone()), lambdas);
+        assert!((e.x - 2.0 * crate::spectrum::d65(400.0)).abs() < 1e-12);
+        assert!((e.y - 2.0 * crate::spectrum::d65(550.0)).abs() < 1e-12);
+        assert!((e.z - 2.0 * crate::spectrum::d65(700.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wrapped_visibility() {
+        let mut light = Diffuse::new(Arc::new(Solid::new(Vec3::ones())));
+        light.visible = false;
+        let hidden: Arc<dyn Material> = Arc::new(light);
+        let white: Arc<dyn Texture> = Arc::new(Solid::new(Vec3::ones()));
+        let wrapped: Vec<Arc<dyn Material>> = vec![
+            Arc::new(MixMaterial::with_ratio(hidden.clone(), hidden.clone(), 0.5)),
+            Arc::new(Coated::new(hidden.clone(), 1.5)),
+            Arc::new(NormalMap::new(hidden.clone(), white.clone())),
+            Arc::new(BumpMap::new(hidden.clone(), white.clone(), 1.0)),
+            Arc::new(Cutout::new(hidden, white)),
+        ];
+        for m in wrapped {
+            assert!(!m.visible_to_camera());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::clamp;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+// 以下向量均在局部着色坐标系中（z 轴为法线）
+
+// 粗糙度 -> GGX alpha（感知线性的平方映射）
+pub fn roughness_to_alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(1e-4)
+}
+
+// anisotropy ∈ [0, 1)，沿切线方向拉长高光
+pub fn anisotropic_alpha(roughness: f64, anisotropy: f64) -> (f64, f64) {
+    let a = roughness_to_alpha(roughness);
+    let aspect = (1.0 - 0.9 * anisotropy).sqrt();
+    ((a / aspect).max(1e-4), (a * aspect).max(1e-4))
+}
+
+// GGX / Trowbridge-Reitz 法线分布
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Ggx {
+        Ggx { alpha_x, alpha_y }
+    }
+
+    pub fn isotropic(alpha: f64) -> Ggx {
+        Ggx::new(alpha, alpha)
+    }
+
+    pub fn d(&self, m: Vec3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let x = m.x / self.alpha_x;
+        let y = m.y / self.alpha_y;
+        let t = x * x + y * y + m.z * m.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * t * t)
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        if w.z.abs() < 1e-12 {
+            return 0.0;
+        }
+        let a2 = (self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2);
+        ((1.0 + a2 / (w.z * w.z)).sqrt() - 1.0) / 2.0
+    }
+
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // 高度相关的 Smith 遮蔽-阴影项
+    pub fn g2(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // 可见法线采样（Heitz 2018），wo.z 须 > 0
+    pub fn sample_vndf(&self, wo: Vec3, u1: f64, u2: f64) -> Vec3 {
+        let vh = Vec3::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).unit();
+        let lensq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if lensq > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / lensq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vec3::cross(vh, t1);
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit()
+    }
+
+    // 可见法线分布的 pdf
+    #[allow(dead_code)]
+    pub fn pdf_vndf(&self, wo: Vec3, m: Vec3) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * (wo * m).max(0.0) * self.d(m) / wo.z
+    }
+}
+
+const ALBEDO_SIZE: usize = 32;
+const ALBEDO_SAMPLES: usize = 16;
+
+// 单次散射 GGX 反射（F = 1）的方向反照率 E(μ) 及其余弦加权平均 E_avg，
+// 用于 Kulla-Conty 多次散射能量补偿；各向异性时只沿 x 方向积分，作为近似
+#[derive(Clone, Copy, Debug)]
+pub struct GgxAlbedo {
+    pub e: [f64; ALBEDO_SIZE], // μ 取各区间中点
+    pub e_avg: f64,
+}
+
+impl GgxAlbedo {
+    pub fn new(ggx: &Ggx) -> GgxAlbedo {
+        let n = ALBEDO_SAMPLES;
+        let mut e = [0.0; ALBEDO_SIZE];
+        for (i, ei) in e.iter_mut().enumerate() {
+            let mu = (i as f64 + 0.5) / ALBEDO_SIZE as f64;
+            let wo = Vec3::new((1.0 - mu * mu).sqrt(), 0.0, mu);
+            // 按可见法线分层采样，权重 G2 / G1
+            let mut sum = 0.0;
+            for a in 0..n {
+                for b in 0..n {
+                    let u1 = (a as f64 + 0.5) / n as f64;
+                    let u2 = (b as f64 + 0.5) / n as f64;
+                    let wi = reflect_about(wo, ggx.sample_vndf(wo, u1, u2));
+                    if wi.z > 0.0 {
+                        sum += ggx.g2(wo, wi) / ggx.g1(wo);
+                    }
+                }
+            }
+            *ei = sum / (n * n) as f64;
+        }
+        let e_avg = cosine_average(|mu| interpolate(&e, mu));
+        GgxAlbedo { e, e_avg }
+    }
+
+    pub fn value(&self, mu: f64) -> f64 {
+        interpolate(&self.e, mu)
+    }
+}
+
+// 在区间中点上线性插值，两端取端点值
+fn interpolate(table: &[f64; ALBEDO_SIZE], mu: f64) -> f64 {
+    let x = (mu * ALBEDO_SIZE as f64 - 0.5).max(0.0);
+    let i = (x as usize).min(ALBEDO_SIZE - 1);
+    let j = (i + 1).min(ALBEDO_SIZE - 1);
+    let t = (x - i as f64).min(1.0);
+    table[i] * (1.0 - t) + table[j] * t
+}
+
+// 2 ∫ f(μ) μ dμ，中点法
+pub fn cosine_average<F>(f: F) -> f64
+where
+    F: Fn(f64) -> f64,
+{
+    let n = ALBEDO_SIZE;
+    let mut sum = 0.0;
+    for i in 0..n {
+        let mu = (i as f64 + 0.5) / n as f64;
+        sum += f(mu) * mu;
+    }
+    2.0 * sum / n as f64
+}
+
+pub fn reflect_about(wo: Vec3, m: Vec3) -> Vec3 {
+    m * (2.0 * (wo * m)) - wo
+}
+
+// 精确的介质菲涅尔反射率（非偏振），eta = 透射侧 / 入射侧折射率
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.abs().min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+fn fresnel_conductor_channel(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let c2 = cos_i * cos_i;
+    let s2 = 1.0 - c2;
+    let e2 = eta * eta;
+    let k2 = k * k;
+    let t0 = e2 - k2 - s2;
+    let a2b2 = (t0 * t0 + 4.0 * e2 * k2).sqrt();
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+    let t1 = a2b2 + c2;
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = c2 * a2b2 + s2 * s2;
+    let t4 = t2 * s2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rs + rp)
+}
+
+// 导体菲涅尔反射率，复折射率 eta + i k 按 RGB 三通道给出
+pub fn fresnel_conductor(cos_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    let c = cos_i.abs().min(1.0);
+    Vec3::new(
+        fresnel_conductor_channel(c, eta.x, k.x),
+        fresnel_conductor_channel(c, eta.y, k.y),
+        fresnel_conductor_channel(c, eta.z, k.z),
+    )
+}
+
+pub fn fresnel_schlick(cos_i: f64, f0: Vec3) -> Vec3 {
+    let m = (1.0 - cos_i.abs().min(1.0)).powi(5);
+    f0 + (Vec3::ones() - f0) * m
+}
+
+// 由 Metal 式的颜色近似金属的消光系数（取 eta = 1，此时正入射反射率恰为 f0）
+pub fn conductor_k(f0: Vec3) -> Vec3 {
+    let k = |f: f64| {
+        let f = clamp(f, 0.0, 0.999);
+        2.0 * (f / (1.0 - f)).sqrt()
+    };
+    Vec3::new(k(f0.x), k(f0.y), k(f0.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresnel_dielectric_normal() {
+        let r0 = ((1.0 - 1.5) / (1.0 + 1.5)) * ((1.0 - 1.5) / (1.0 + 1.5));
+        assert!((fresnel_dielectric(1.0, 1.5) - r0).abs() < 1e-12);
+        assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+    }
+
+    #[test]
+    fn test_fresnel_conductor_limit() {
+        // k = 0 时退化为介质菲涅尔
+        let f = fresnel_conductor(0.6, Vec3::ones() * 1.5, Vec3::zero());
+        assert!((f.x - fresnel_dielectric(0.6, 1.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_albedo() {
+        // 接近镜面时几乎没有能量损失，粗糙表面损失明显
+        let smooth = GgxAlbedo::new(&Ggx::isotropic(1e-4));
+        assert!((smooth.e_avg - 1.0).abs() < 1e-3);
+        let rough = GgxAlbedo::new(&Ggx::isotropic(1.0));
+        assert!(rough.e_avg > 0.0 && rough.e_avg < 0.9);
+        assert!(rough.e.iter().all(|&e| e > 0.0 && e <= 1.0));
+    }
+
+    #[test]
+    fn test_vndf_upper_hemisphere() {
+        let ggx = Ggx::new(0.5, 0.2);
+        let wo = Vec3::new(0.3, -0.4, 0.5).unit();
+        let m = ggx.sample_vndf(wo, 0.37, 0.81);
+        assert!(m.z > 0.0);
+        assert!((m.length() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::vec3::Vec3;
+
+// 以 w 为 z 轴的正交基，用于在局部坐标系中采样方向
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w(n: Vec3) -> Onb {
+        let w = n.unit();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross(w, a).unit();
+        let u = Vec3::cross(v, w);
+        Onb { u, v, w }
+    }
+
+    // 以 w 为法线、u 尽量贴近给定切线
+    pub fn build_from_wu(n: Vec3, tangent: Vec3) -> Onb {
+        let w = n.unit();
+        let t = tangent - w * (tangent * w);
+        if t.squared_length() < 1e-12 {
+            return Onb::build_from_w(n);
+        }
+        let u = t.unit();
+        let v = Vec3::cross(w, u);
+        Onb { u, v, w }
+    }
+
+    // 局部坐标 -> 世界坐标
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        self.u * a.x + self.v * a.y + self.w * a.z
+    }
+
+    // 世界坐标 -> 局部坐标
+    pub fn world_to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(a * self.u, a * self.v, a * self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orthonormal() {
+        let onb = Onb::build_from_w(Vec3::new(1.0, 2.0, -3.0));
+        assert!((onb.u.length() - 1.0).abs() < 1e-12);
+        assert!((onb.v.length() - 1.0).abs() < 1e-12);
+        assert!((onb.u * onb.v).abs() < 1e-12);
+        assert!((onb.u * onb.w).abs() < 1e-12);
+        assert!((onb.v * onb.w).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let onb = Onb::build_from_w(Vec3::new(0.0, 0.0, -1.0));
+        let a = Vec3::new(0.3, -0.2, 0.9);
+        let b = onb.world_to_local(onb.local(a));
+        assert!((a - b).length() < 1e-12);
+    }
+}
@@ -0,0 +1,549 @@
+use crate::clamp;
+use crate::perlin::Perlin;
+use crate::texture::{Solid, Texture};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// 程序纹理库：颜色与强度参数都是 Arc<dyn Texture>，纹理之间可以任意嵌套
+
+// 世界坐标到纹理空间：先平移，再按 x、y、z 顺序旋转（弧度），最后逐轴缩放
+#[derive(Clone, Copy, Debug)]
+pub struct TextureTransform {
+    pub offset: Vec3,
+    pub rotation: Vec3,
+    pub scale: Vec3,
+}
+
+impl TextureTransform {
+    pub fn new(offset: Vec3, rotation: Vec3, scale: Vec3) -> TextureTransform {
+        TextureTransform {
+            offset,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn identity() -> TextureTransform {
+        TextureTransform::uniform(1.0)
+    }
+
+    pub fn uniform(scale: f64) -> TextureTransform {
+        TextureTransform::new(Vec3::zero(), Vec3::zero(), Vec3::ones() * scale)
+    }
+
+    pub fn apply(&self, p: Vec3) -> Vec3 {
+        let q = p - self.offset;
+        let (s, c) = self.rotation.x.sin_cos();
+        let q = Vec3::new(q.x, c * q.y - s * q.z, s * q.y + c * q.z);
+        let (s, c) = self.rotation.y.sin_cos();
+        let q = Vec3::new(c * q.x + s * q.z, q.y, -s * q.x + c * q.z);
+        let (s, c) = self.rotation.z.sin_cos();
+        let q = Vec3::new(c * q.x - s * q.y, s * q.x + c * q.y, q.z);
+        Vec3::elemul(q, self.scale)
+    }
+}
+
+fn solid(c: Vec3) -> Arc<dyn Texture> {
+    Arc::new(Solid::new(c))
+}
+
+fn mix(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+fn smoothstep(e0: f64, e1: f64, x: f64) -> f64 {
+    let t = clamp((x - e0) / (e1 - e0), 0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// 整数格点的确定性哈希，返回 [0, 1)
+fn hash3(i: i64, j: i64, k: i64, seed: u32) -> f64 {
+    let mut h = (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (j as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (k as u64).wrapping_mul(0x1656_67b1_9e37_79f9)
+        ^ (seed as u64).wrapping_mul(0x27d4_eb2f_1656_67c5);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// 分形布朗运动，结果大致落在 [-1, 1]
+pub fn fbm(noise: &Perlin, p: Vec3, octaves: i32, lacunarity: f64, gain: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut freq = 1.0;
+    let mut amp = 1.0;
+    for _ in 0..octaves {
+        sum += amp * noise.noise(p * freq);
+        freq *= lacunarity;
+        amp *= gain;
+    }
+    sum
+}
+
+// Musgrave 脊状多重分形：每层信号由上一层加权，谷底平滑、脊线尖锐，结果在 [0, 1]
+pub fn ridged(
+    noise: &Perlin,
+    p: Vec3,
+    octaves: i32,
+    lacunarity: f64,
+    gain: f64,
+    offset: f64,
+) -> f64 {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    let mut freq = 1.0;
+    let mut amp = 1.0;
+    let mut weight = 1.0;
+    for _ in 0..octaves {
+        let mut s = offset - noise.noise(p * freq).abs();
+        s = s * s * weight;
+        weight = clamp(2.0 * s, 0.0, 1.0);
+        sum += s * amp;
+        norm += amp * offset * offset;
+        freq *= lacunarity;
+        amp *= gain;
+    }
+    clamp(sum / norm, 0.0, 1.0)
+}
+
+// Worley 噪声：最近与次近特征点的距离，以及最近特征点所在格子的随机值
+pub fn cellular(p: Vec3, jitter: f64, seed: u32) -> (f64, f64, f64) {
+    let (ci, cj, ck) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+    let mut f1 = f64::INFINITY;
+    let mut f2 = f64::INFINITY;
+    let mut id = 0.0;
+    for di in -1..=1 {
+        for dj in -1..=1 {
+            for dk in -1..=1 {
+                let (i, j, k) = (ci + di, cj + dj, ck + dk);
+                let feature = Vec3::new(
+                    i as f64 + 0.5 + jitter * (hash3(i, j, k, seed) - 0.5),
+                    j as f64 + 0.5 + jitter * (hash3(i, j, k, seed + 1) - 0.5),
+                    k as f64 + 0.5 + jitter * (hash3(i, j, k, seed + 2) - 0.5),
+                );
+                let d = (feature - p).length();
+                if d < f1 {
+                    f2 = f1;
+                    f1 = d;
+                    id = hash3(i, j, k, seed + 3);
+                } else if d < f2 {
+                    f2 = d;
+                }
+            }
+        }
+    }
+    (f1, f2, id)
+}
+
+#[derive(Clone)]
+pub struct Fbm {
+    pub noise: Perlin,
+    pub transform: TextureTransform,
+    pub octaves: i32,
+    pub lacunarity: f64,
+    pub gain: f64,
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+}
+
+impl Fbm {
+    pub fn new(noise: Perlin, octaves: i32, low: Arc<dyn Texture>, high: Arc<dyn Texture>) -> Fbm {
+        Fbm {
+            noise,
+            transform: TextureTransform::identity(),
+            octaves,
+            lacunarity: 2.0,
+            gain: 0.5,
+            low,
+            high,
+        }
+    }
+
+    // 黑白输出，可作为其他纹理的强度参数
+    #[allow(dead_code)]
+    pub fn scalar(noise: Perlin, octaves: i32) -> Fbm {
+        Fbm::new(noise, octaves, solid(Vec3::zero()), solid(Vec3::ones()))
+    }
+}
+
+impl Texture for Fbm {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let n = fbm(&self.noise, q, self.octaves, self.lacunarity, self.gain);
+        let t = clamp(0.5 + 0.5 * n, 0.0, 1.0);
+        mix(self.low.value(u, v, p), self.high.value(u, v, p), t)
+    }
+}
+
+#[derive(Clone)]
+pub struct Ridged {
+    pub noise: Perlin,
+    pub transform: TextureTransform,
+    pub octaves: i32,
+    pub lacunarity: f64,
+    pub gain: f64,
+    pub offset: f64,
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+}
+
+impl Ridged {
+    pub fn new(
+        noise: Perlin,
+        octaves: i32,
+        low: Arc<dyn Texture>,
+        high: Arc<dyn Texture>,
+    ) -> Ridged {
+        Ridged {
+            noise,
+            transform: TextureTransform::identity(),
+            octaves,
+            lacunarity: 2.0,
+            gain: 0.5,
+            offset: 1.0,
+            low,
+            high,
+        }
+    }
+}
+
+impl Texture for Ridged {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let t = ridged(
+            &self.noise,
+            q,
+            self.octaves,
+            self.lacunarity,
+            self.gain,
+            self.offset,
+        );
+        mix(self.low.value(u, v, p), self.high.value(u, v, p), t)
+    }
+}
+
+#[derive(Clone)]
+pub struct Marble {
+    // 沿纹理空间 x 方向的正弦条纹，被湍流扭曲成纹理
+    pub noise: Perlin,
+    pub transform: TextureTransform,
+    pub octaves: i32,
+    pub frequency: f64,               // 每单位长度的条纹数（弧度）
+    pub distortion: Arc<dyn Texture>, // 湍流扭曲强度，取 x 分量
+    pub sharpness: f64,               // > 1 时纹路更细
+    pub base: Arc<dyn Texture>,
+    pub vein: Arc<dyn Texture>,
+}
+
+impl Marble {
+    pub fn new(noise: Perlin, base: Arc<dyn Texture>, vein: Arc<dyn Texture>) -> Marble {
+        Marble {
+            noise,
+            transform: TextureTransform::identity(),
+            octaves: 7,
+            frequency: 4.0,
+            distortion: solid(Vec3::ones() * 10.0),
+            sharpness: 1.0,
+            base,
+            vein,
+        }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let k = self.distortion.value(u, v, p).x;
+        let s = 0.5 * (1.0 + (self.frequency * q.x + k * self.noise.turb(q, self.octaves)).sin());
+        let t = (1.0 - s).powf(self.sharpness);
+        mix(self.base.value(u, v, p), self.vein.value(u, v, p), t)
+    }
+}
+
+#[derive(Clone)]
+pub struct Wood {
+    // 以纹理空间 y 轴为树干中心的年轮
+    pub noise: Perlin,
+    pub transform: TextureTransform,
+    pub rings: f64,                   // 每单位半径的年轮数
+    pub distortion: Arc<dyn Texture>, // 年轮的扰动幅度（以年轮宽度计），取 x 分量
+    pub grain: f64,                   // 扰动噪声的频率
+    pub light: Arc<dyn Texture>,
+    pub dark: Arc<dyn Texture>,
+}
+
+impl Wood {
+    pub fn new(noise: Perlin, rings: f64, light: Arc<dyn Texture>, dark: Arc<dyn Texture>) -> Wood {
+        Wood {
+            noise,
+            transform: TextureTransform::identity(),
+            rings,
+            distortion: solid(Vec3::ones() * 0.5),
+            grain: 2.0,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let k = self.distortion.value(u, v, p).x;
+        let r = (q.x * q.x + q.z * q.z).sqrt() * self.rings + k * self.noise.noise(q * self.grain);
+        let ring = r - r.floor();
+        // 早材逐渐过渡到颜色较深的晚材，随后突变回下一圈早材
+        let t = smoothstep(0.0, 0.75, ring) - smoothstep(0.9, 1.0, ring);
+        mix(self.light.value(u, v, p), self.dark.value(u, v, p), t)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorleyMode {
+    F1,        // 到最近特征点的距离
+    F2,        // 到次近特征点的距离
+    Edge(f64), // F2 - F1 小于给定宽度处为格子边界
+    Cell,      // 每个格子一个随机值
+}
+
+#[derive(Clone)]
+pub struct Worley {
+    pub transform: TextureTransform,
+    pub mode: WorleyMode,
+    pub jitter: f64, // 特征点在格子内的随机偏移，0 为规则网格，1 为完全随机
+    pub seed: u32,
+    pub near: Arc<dyn Texture>,
+    pub far: Arc<dyn Texture>,
+}
+
+impl Worley {
+    pub fn new(mode: WorleyMode, near: Arc<dyn Texture>, far: Arc<dyn Texture>) -> Worley {
+        Worley {
+            transform: TextureTransform::identity(),
+            mode,
+            jitter: 1.0,
+            seed: 0,
+            near,
+            far,
+        }
+    }
+}
+
+impl Texture for Worley {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let (f1, f2, id) = cellular(q, self.jitter, self.seed);
+        let t = match self.mode {
+            WorleyMode::F1 => clamp(f1, 0.0, 1.0),
+            WorleyMode::F2 => clamp(f2 / 1.5, 0.0, 1.0),
+            WorleyMode::Edge(width) => smoothstep(0.0, width, f2 - f1),
+            WorleyMode::Cell => id,
+        };
+        mix(self.near.value(u, v, p), self.far.value(u, v, p), t)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear,    // 纹理空间 x 从 0 到 1
+    Spherical, // 纹理空间原点处为 1，半径 1 处降为 0
+}
+
+// 标量渐变，常作为 ColorRamp 的输入
+#[derive(Clone, Copy)]
+pub struct Gradient {
+    pub transform: TextureTransform,
+    pub kind: GradientKind,
+}
+
+impl Gradient {
+    #[allow(dead_code)]
+    pub fn new(kind: GradientKind) -> Gradient {
+        Gradient {
+            transform: TextureTransform::identity(),
+            kind,
+        }
+    }
+}
+
+impl Texture for Gradient {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        let t = match self.kind {
+            GradientKind::Linear => q.x,
+            GradientKind::Spherical => 1.0 - q.length(),
+        };
+        Vec3::ones() * clamp(t, 0.0, 1.0)
+    }
+}
+
+#[derive(Clone)]
+pub struct ColorRamp {
+    // 把 input 的 x 分量按色标线性插值映射为颜色，色标本身也可以是纹理
+    pub input: Arc<dyn Texture>,
+    pub stops: Vec<(f64, Arc<dyn Texture>)>,
+}
+
+impl ColorRamp {
+    // 位置为 NaN 的色标无法排序，直接丢弃
+    pub fn new(input: Arc<dyn Texture>, mut stops: Vec<(f64, Arc<dyn Texture>)>) -> ColorRamp {
+        stops.retain(|s| !s.0.is_nan());
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        ColorRamp { input, stops }
+    }
+
+    pub fn from_colors(input: Arc<dyn Texture>, stops: &[(f64, Vec3)]) -> ColorRamp {
+        ColorRamp::new(input, stops.iter().map(|&(t, c)| (t, solid(c))).collect())
+    }
+}
+
+impl Texture for ColorRamp {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let t = self.input.value(u, v, p).x;
+        let n = self.stops.len();
+        if n == 0 {
+            return Vec3::zero();
+        }
+        if t <= self.stops[0].0 {
+            return self.stops[0].1.value(u, v, p);
+        }
+        for w in self.stops.windows(2) {
+            let (t0, t1) = (w[0].0, w[1].0);
+            if t <= t1 {
+                let s = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                return mix(w[0].1.value(u, v, p), w[1].1.value(u, v, p), s);
+            }
+        }
+        self.stops[n - 1].1.value(u, v, p)
+    }
+}
+
+#[derive(Clone)]
+pub struct Bricks {
+    // 砖块排列在纹理空间的 xy 平面内，沿 z 方向不变
+    pub transform: TextureTransform,
+    pub width: f64,
+    pub height: f64,
+    pub mortar: f64,     // 灰缝宽度
+    pub row_offset: f64, // 相邻两行错开的比例，0.5 为顺砌，0 为方砖
+    pub variation: f64,  // 每块砖亮度的随机变化幅度
+    pub brick: Arc<dyn Texture>,
+    pub mortar_color: Arc<dyn Texture>,
+}
+
+impl Bricks {
+    pub fn new(
+        width: f64,
+        height: f64,
+        mortar: f64,
+        brick: Arc<dyn Texture>,
+        mortar_color: Arc<dyn Texture>,
+    ) -> Bricks {
+        Bricks {
+            transform: TextureTransform::identity(),
+            width,
+            height,
+            mortar,
+            row_offset: 0.5,
+            variation: 0.2,
+            brick,
+            mortar_color,
+        }
+    }
+
+    // 对齐的方形瓷砖
+    #[allow(dead_code)]
+    pub fn tiles(
+        size: f64,
+        grout: f64,
+        tile: Arc<dyn Texture>,
+        grout_color: Arc<dyn Texture>,
+    ) -> Bricks {
+        let mut b = Bricks::new(size, size, grout, tile, grout_color);
+        b.row_offset = 0.0;
+        b.variation = 0.0;
+        b
+    }
+
+    // 返回砖块编号，位于灰缝中时为 None
+    fn locate(&self, q: Vec3) -> Option<(i64, i64)> {
+        let row = (q.y / self.height).floor();
+        let x = q.x / self.width + row * self.row_offset;
+        let col = x.floor();
+        let fx = (x - col) * self.width;
+        let fy = (q.y / self.height - row) * self.height;
+        let half = 0.5 * self.mortar;
+        if fx < half || fx > self.width - half || fy < half || fy > self.height - half {
+            return None;
+        }
+        Some((col as i64, row as i64))
+    }
+}
+
+impl Texture for Bricks {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = self.transform.apply(p);
+        match self.locate(q) {
+            Some((i, j)) => {
+                let k = 1.0 - self.variation * hash3(i, j, 0, 7);
+                self.brick.value(u, v, p) * k
+            }
+            None => self.mortar_color.value(u, v, p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_range() {
+        for i in -20..20 {
+            let h = hash3(i, 3 * i, -i, 1);
+            assert!((0.0..1.0).contains(&h));
+        }
+        assert_eq!(hash3(1, 2, 3, 0), hash3(1, 2, 3, 0));
+    }
+
+    #[test]
+    fn test_bricks_mortar() {
+        let b = Bricks::new(2.0, 1.0, 0.1, solid(Vec3::ones()), solid(Vec3::zero()));
+        assert_eq!(b.locate(Vec3::new(1.0, 0.5, 0.0)), Some((0, 0)));
+        assert_eq!(b.locate(Vec3::new(1.0, 0.01, 0.0)), None);
+        // 第二行错开半块砖，x = 1 处正好是灰缝
+        assert_eq!(b.locate(Vec3::new(1.0, 1.5, 0.0)), None);
+    }
+
+    #[test]
+    fn test_color_ramp() {
+        let input = solid(Vec3::ones() * 0.75);
+        let ramp = ColorRamp::from_colors(
+            input,
+            &[
+                (1.0, Vec3::new(0.0, 0.0, 1.0)),
+                (0.5, Vec3::new(1.0, 0.0, 0.0)),
+            ],
+        );
+        let c = ramp.value(0.0, 0.0, Vec3::zero());
+        assert!((c - Vec3::new(0.5, 0.0, 0.5)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_color_ramp_nan_stop() {
+        let ramp = ColorRamp::from_colors(
+            solid(Vec3::ones() * 0.75),
+            &[
+                (1.0, Vec3::new(0.0, 0.0, 1.0)),
+                (f64::NAN, Vec3::new(0.0, 1.0, 0.0)),
+                (0.5, Vec3::new(1.0, 0.0, 0.0)),
+            ],
+        );
+        assert_eq!(ramp.stops.len(), 2);
+        let c = ramp.value(0.0, 0.0, Vec3::zero());
+        assert!((c - Vec3::new(0.5, 0.0, 0.5)).length() < 1e-12);
+    }
+}
@@ -1,17 +1,121 @@
 #![allow(warnings, unused)]
 
+use crate::spectrum::Dispersion;
 pub use crate::vec3::Vec3;
 
+// 嵌套介质栈中的一项（玻璃、液体、冰……）
+#[derive(Clone, Copy, Debug)]
+pub struct MediumEntry {
+    pub id: usize,
+    pub priority: i32, // 数值大者优先，重叠区域属于优先级最高的介质
+    pub ior: f64,
+    pub dispersion: Option<Dispersion>,
+    pub absorption: Vec3,
+}
+
+impl MediumEntry {
+    // 光线带有波长时按色散公式计算折射率
+    pub fn ior_at(&self, wavelength: Option<f64>) -> f64 {
+        match (self.dispersion, wavelength) {
+            (Some(d), Some(lambda)) => d.ior(lambda),
+            _ => self.ior,
+        }
+    }
+}
+
+pub const MAX_MEDIA: usize = 4;
+
+// 光线当前所处的介质，栈满时新进入的介质被忽略
+#[derive(Clone, Copy, Debug)]
+pub struct MediumStack {
+    entries: [Option<MediumEntry>; MAX_MEDIA],
+    len: usize,
+}
+
+impl MediumStack {
+    pub fn new() -> MediumStack {
+        MediumStack {
+            entries: [None; MAX_MEDIA],
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.entries[..self.len]
+            .iter()
+            .any(|e| e.map_or(false, |e| e.id == id))
+    }
+
+    pub fn push(&mut self, entry: MediumEntry) {
+        if self.len < MAX_MEDIA {
+            self.entries[self.len] = Some(entry);
+            self.len += 1;
+        }
+    }
+
+    // 移除最近一次进入的 id
+    pub fn remove(&mut self, id: usize) {
+        if let Some(i) = (0..self.len)
+            .rev()
+            .find(|&i| self.entries[i].unwrap().id == id)
+        {
+            for j in i..self.len - 1 {
+                self.entries[j] = self.entries[j + 1];
+            }
+            self.len -= 1;
+            self.entries[self.len] = None;
+        }
+    }
+
+    // 优先级最高的介质，同优先级取最后进入的
+    pub fn top(&self) -> Option<MediumEntry> {
+        let mut best: Option<MediumEntry> = None;
+        for e in self.entries[..self.len].iter().flatten() {
+            if best.map_or(true, |b| e.priority >= b.priority) {
+                best = Some(*e);
+            }
+        }
+        best
+    }
+}
+
+// 光线微分：相机上相邻一个像素的两条偏移光线，用于估计交点处纹理的覆盖范围
+#[derive(Clone, Copy, Debug)]
+pub struct RayDifferential {
+    pub rx_org: Vec3,
+    pub rx_drc: Vec3,
+    pub ry_org: Vec3,
+    pub ry_drc: Vec3,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub org: Vec3,
     pub drc: Vec3,
     pub tm: f64,
+    pub media: Option<MediumStack>, // None 表示沿用上一段光线的介质
+    pub wavelength: Option<f64>,    // 经过色散介质后采样的波长（nm），None 表示 RGB 光线
+    pub spectral: Option<Vec3>,     // 光谱模式下路径携带的三个波长（nm），x 为主波长
+    pub screen: Option<(f64, f64)>, // 相机光线在输出图像上的 uv，散射后为 None
+    pub differential: Option<RayDifferential>, // 只在相机光线及其镜面反射/折射上存在
 }
 
 impl Ray {
     pub fn new(org: Vec3, drc: Vec3, tm: f64) -> Self {
-        Self { org, drc, tm }
+        Self {
+            org,
+            drc,
+            tm,
+            media: None,
+            wavelength: None,
+            spectral: None,
+            screen: None,
+            differential: None,
+        }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
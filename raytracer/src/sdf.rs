@@ -0,0 +1,480 @@
+use crate::aabb::AABB;
+use crate::clamp;
+use crate::hittable::{get_sphere_uv, sphere_dpdu, Hitrecord, Object};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// 有向距离场：返回点到表面的（近似）距离，内部为负
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Vec3) -> f64;
+}
+
+// 任意闭包都可以直接作为距离函数使用
+impl<F> Sdf for F
+where
+    F: Fn(Vec3) -> f64 + Send + Sync,
+{
+    fn distance(&self, p: Vec3) -> f64 {
+        self(p)
+    }
+}
+
+fn vabs(v: Vec3) -> Vec3 {
+    Vec3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+fn vmax(v: Vec3, s: f64) -> Vec3 {
+    Vec3::new(v.x.max(s), v.y.max(s), v.z.max(s))
+}
+
+fn mix(a: f64, b: f64, t: f64) -> f64 {
+    a * (1.0 - t) + b * t
+}
+
+// ---------- 基本形体（均以原点为中心） ----------
+
+#[derive(Copy, Clone)]
+pub struct SdfSphere {
+    pub radius: f64,
+}
+
+impl SdfSphere {
+    #[allow(dead_code)]
+    pub fn new(radius: f64) -> SdfSphere {
+        SdfSphere { radius }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Vec3) -> f64 {
+        p.length() - self.radius
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct SdfBox {
+    pub half: Vec3,
+}
+
+impl SdfBox {
+    pub fn new(half: Vec3) -> SdfBox {
+        SdfBox { half }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Vec3) -> f64 {
+        let q = vabs(p) - self.half;
+        vmax(q, 0.0).length() + q.x.max(q.y.max(q.z)).min(0.0)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct RoundBox {
+    pub half: Vec3,
+    pub radius: f64,
+}
+
+impl RoundBox {
+    #[allow(dead_code)]
+    pub fn new(half: Vec3, radius: f64) -> RoundBox {
+        RoundBox { half, radius }
+    }
+}
+
+impl Sdf for RoundBox {
+    fn distance(&self, p: Vec3) -> f64 {
+        SdfBox::new(self.half - self.radius).distance(p) - self.radius
+    }
+}
+
+// 位于 xz 平面的圆环
+#[derive(Copy, Clone)]
+pub struct Torus {
+    pub major: f64,
+    pub minor: f64,
+}
+
+impl Torus {
+    #[allow(dead_code)]
+    pub fn new(major: f64, minor: f64) -> Torus {
+        Torus { major, minor }
+    }
+}
+
+impl Sdf for Torus {
+    fn distance(&self, p: Vec3) -> f64 {
+        let qx = (p.x * p.x + p.z * p.z).sqrt() - self.major;
+        (qx * qx + p.y * p.y).sqrt() - self.minor
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Capsule {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f64,
+}
+
+impl Capsule {
+    #[allow(dead_code)]
+    pub fn new(a: Vec3, b: Vec3, radius: f64) -> Capsule {
+        Capsule { a, b, radius }
+    }
+}
+
+impl Sdf for Capsule {
+    fn distance(&self, p: Vec3) -> f64 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let h = clamp((pa * ba) / (ba * ba), 0.0, 1.0);
+        (pa - ba * h).length() - self.radius
+    }
+}
+
+// 正三角柱：截面在 xy 平面内，顶点朝 +y，沿 z 方向拉伸
+#[derive(Copy, Clone)]
+pub struct TriPrism {
+    pub side: f64, // 截面边长
+    pub half_depth: f64,
+}
+
+impl TriPrism {
+    pub fn new(side: f64, half_depth: f64) -> TriPrism {
+        TriPrism { side, half_depth }
+    }
+}
+
+impl Sdf for TriPrism {
+    fn distance(&self, p: Vec3) -> f64 {
+        // 截面内切圆半径为 side / (2√3)，三条边到中心的距离相同
+        let r = self.side / (2.0 * 3.0_f64.sqrt());
+        let edge = (p.x.abs() * 0.866_025 + p.y * 0.5).max(-p.y) - r;
+        edge.max(p.z.abs() - self.half_depth)
+    }
+}
+
+// ---------- 组合与变形 ----------
+
+#[derive(Clone)]
+pub struct Union {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+}
+
+impl Union {
+    #[allow(dead_code)]
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Union {
+        Union { a, b }
+    }
+}
+
+impl Sdf for Union {
+    fn distance(&self, p: Vec3) -> f64 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+}
+
+#[derive(Clone)]
+pub struct Intersection {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+}
+
+impl Intersection {
+    #[allow(dead_code)]
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Intersection {
+        Intersection { a, b }
+    }
+}
+
+impl Sdf for Intersection {
+    fn distance(&self, p: Vec3) -> f64 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+}
+
+// a 减去 b
+#[derive(Clone)]
+pub struct Subtraction {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+}
+
+impl Subtraction {
+    #[allow(dead_code)]
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Subtraction {
+        Subtraction { a, b }
+    }
+}
+
+impl Sdf for Subtraction {
+    fn distance(&self, p: Vec3) -> f64 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+}
+
+// 多项式平滑并，k 为过渡宽度
+#[derive(Clone)]
+pub struct SmoothUnion {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+    pub k: f64,
+}
+
+impl SmoothUnion {
+    #[allow(dead_code)]
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> SmoothUnion {
+        SmoothUnion { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: Vec3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        let h = clamp(0.5 + 0.5 * (d2 - d1) / self.k, 0.0, 1.0);
+        mix(d2, d1, h) - self.k * h * (1.0 - h)
+    }
+}
+
+// 绕 y 轴扭转，每单位高度旋转 k 弧度（距离不再精确，需减小步长）
+#[derive(Clone)]
+pub struct Twist {
+    pub inner: Arc<dyn Sdf>,
+    pub k: f64,
+}
+
+impl Twist {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Sdf>, k: f64) -> Twist {
+        Twist { inner, k }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: Vec3) -> f64 {
+        let c = (self.k * p.y).cos();
+        let s = (self.k * p.y).sin();
+        let q = Vec3::new(c * p.x - s * p.z, p.y, s * p.x + c * p.z);
+        self.inner.distance(q)
+    }
+}
+
+// 无限重复，period 某分量为 0 时该轴不重复
+#[derive(Clone)]
+pub struct Repeat {
+    pub inner: Arc<dyn Sdf>,
+    pub period: Vec3,
+}
+
+impl Repeat {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Sdf>, period: Vec3) -> Repeat {
+        Repeat { inner, period }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: Vec3) -> f64 {
+        let rep = |x: f64, c: f64| {
+            if c > 0.0 {
+                x - c * (x / c).round()
+            } else {
+                x
+            }
+        };
+        let q = Vec3::new(
+            rep(p.x, self.period.x),
+            rep(p.y, self.period.y),
+            rep(p.z, self.period.z),
+        );
+        self.inner.distance(q)
+    }
+}
+
+#[derive(Clone)]
+pub struct Offset {
+    pub inner: Arc<dyn Sdf>,
+    pub offset: Vec3,
+}
+
+impl Offset {
+    #[allow(dead_code)]
+    pub fn new(inner: Arc<dyn Sdf>, offset: Vec3) -> Offset {
+        Offset { inner, offset }
+    }
+}
+
+impl Sdf for Offset {
+    fn distance(&self, p: Vec3) -> f64 {
+        self.inner.distance(p - self.offset)
+    }
+}
+
+// ---------- 球面追踪物体 ----------
+
+#[derive(Clone)]
+pub struct SdfObject {
+    pub sdf: Arc<dyn Sdf>,
+    pub bbox: AABB,
+    pub mat_ptr: Arc<dyn Material>,
+    pub max_steps: usize,
+    pub epsilon: f64,
+    pub step_scale: f64, // 距离场不满足 1-Lipschitz 时（扭转、平滑并）取 < 1
+}
+
+impl SdfObject {
+    // bbox 必须完整包住表面，求交只在盒内进行
+    pub fn new(sdf: Arc<dyn Sdf>, bbox: AABB, mat_ptr: Arc<dyn Material>) -> SdfObject {
+        let diag = (bbox._max - bbox._min).length();
+        SdfObject {
+            sdf,
+            bbox,
+            mat_ptr,
+            max_steps: 256,
+            epsilon: 1e-5 * diag,
+            step_scale: 1.0,
+        }
+    }
+
+    // 由梯度求外法线（四面体差分）
+    pub fn normal(&self, p: Vec3) -> Vec3 {
+        let h = self.epsilon;
+        let k0 = Vec3::new(1.0, -1.0, -1.0);
+        let k1 = Vec3::new(-1.0, -1.0, 1.0);
+        let k2 = Vec3::new(-1.0, 1.0, -1.0);
+        let k3 = Vec3::new(1.0, 1.0, 1.0);
+        let g = k0 * self.sdf.distance(p + k0 * h)
+            + k1 * self.sdf.distance(p + k1 * h)
+            + k2 * self.sdf.distance(p + k2 * h)
+            + k3 * self.sdf.distance(p + k3 * h);
+        if g.squared_length() == 0.0 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        g.unit()
+    }
+}
+
+impl Object for SdfObject {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let (t0, t1) = self.bbox.hit_interval(r, t_min, t_max)?;
+        let len = r.drc.length();
+        let eps = self.epsilon;
+
+        // 起点若贴着表面（例如刚从该表面反射/折射），按光线方向决定在哪一侧行进
+        let d0 = self.sdf.distance(r.at(t0));
+        let on_surface = d0.abs() < eps;
+        let sign = if on_surface {
+            if self.normal(r.at(t0)) * r.drc > 0.0 {
+                1.0
+            } else {
+                -1.0
+            }
+        } else {
+            d0.signum()
+        };
+
+        let mut t = t0;
+        for _ in 0..self.max_steps {
+            let p = r.at(t);
+            let d = sign * self.sdf.distance(p);
+            if d < eps && (!on_surface || (t - t0) * len > 2.0 * eps) {
+                let outward_normal = self.normal(p);
+                let mut rec = Hitrecord::new(p, outward_normal, t, self.mat_ptr.clone());
+                rec.set_face_normal(r, outward_normal);
+                rec.set_uv(get_sphere_uv(&outward_normal));
+                // uv 由法线的球面坐标给出，切线取对应单位球面上的偏导方向
+                let (dpdu, dpdv) = sphere_dpdu(&outward_normal, 1.0);
+                rec.set_dpdu(dpdu, dpdv);
+                return Some(rec);
+            }
+            t += d.max(eps) * self.step_scale / len;
+            if t > t1 {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::Solid;
+
+    fn object(sdf: Arc<dyn Sdf>, half: f64) -> SdfObject {
+        let mat = Arc::new(Lambertian::new(Arc::new(Solid::new(Vec3::ones()))));
+        let bbox = AABB::new(&(-Vec3::ones() * half), &(Vec3::ones() * half));
+        SdfObject::new(sdf, bbox, mat)
+    }
+
+    fn check(obj: &SdfObject, r: &Ray, t: f64, n: Vec3) {
+        let rec = obj.hit(r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - t).abs() < 1e-3, "t = {}, expected {}", rec.t, t);
+        assert!((rec.n - n).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_sphere() {
+        let obj = object(Arc::new(SdfSphere::new(1.0)), 1.5);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        check(&obj, &Ray::new(Vec3::new(0.0, 0.0, -5.0), z, 0.0), 4.0, -z);
+        // 偏离轴线 0.6：t = 5 - sqrt(1 - 0.36)
+        check(
+            &obj,
+            &Ray::new(Vec3::new(0.0, 0.6, -5.0), z, 0.0),
+            4.2,
+            Vec3::new(0.0, 0.6, -0.8),
+        );
+        assert!(obj
+            .hit(
+                &Ray::new(Vec3::new(0.0, 1.01, -5.0), z, 0.0),
+                0.001,
+                f64::INFINITY
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_box() {
+        let obj = object(Arc::new(SdfBox::new(Vec3::new(1.0, 2.0, 3.0))), 3.5);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        check(&obj, &Ray::new(Vec3::new(0.3, 0.4, -10.0), z, 0.0), 7.0, -z);
+        check(&obj, &Ray::new(Vec3::new(-5.0, 1.5, 2.5), x, 0.0), 4.0, -x);
+        assert!(obj
+            .hit(
+                &Ray::new(Vec3::new(0.0, 2.1, -10.0), z, 0.0),
+                0.001,
+                f64::INFINITY
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_round_box() {
+        let obj = object(Arc::new(RoundBox::new(Vec3::ones(), 0.25)), 1.5);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        check(&obj, &Ray::new(Vec3::new(0.0, 0.0, -5.0), z, 0.0), 4.0, -z);
+        // 沿对角线射向角上的圆角：球心 (0.75, 0.75, 0.75)，半径 0.25
+        let d = Vec3::new(1.0, 1.0, 1.0).unit();
+        let t = 3.0_f64.sqrt() * 4.25 - 0.25;
+        check(&obj, &Ray::new(Vec3::ones() * 5.0, -d, 0.0), t, d);
+        // 直角盒会被击中，但落在圆角棱外
+        assert!(obj
+            .hit(
+                &Ray::new(Vec3::new(0.98, 0.98, -5.0), z, 0.0),
+                0.001,
+                f64::INFINITY
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::clamp;
+use crate::vec3::Vec3;
+
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 780.0;
+
+// CIE 1931 标准观察者的多段高斯拟合（Wyman, Sloan, Shirley 2013），波长单位 nm
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let g = |x: f64, mu: f64, s1: f64, s2: f64| {
+        let t = (x - mu) / if x < mu { s1 } else { s2 };
+        (-0.5 * t * t).exp()
+    };
+    let x = 1.056 * g(lambda, 599.8, 37.9, 31.0) + 0.362 * g(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * g(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * g(lambda, 568.8, 46.9, 40.5) + 0.286 * g(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * g(lambda, 437.0, 11.8, 36.0) + 0.681 * g(lambda, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+// XYZ -> 线性 sRGB（D65 白点）
+pub fn xyz_to_rgb(c: Vec3) -> Vec3 {
+    Vec3::new(
+        3.240_454_2 * c.x - 1.537_138_5 * c.y - 0.498_531_4 * c.z,
+        -0.969_266_0 * c.x + 1.876_010_8 * c.y + 0.041_556_0 * c.z,
+        0.055_643_4 * c.x - 0.204_025_9 * c.y + 1.057_225_2 * c.z,
+    )
+}
+
+// 普朗克黑体辐射，lambda 单位 nm，返回值只用于相对比较
+pub fn planck(lambda: f64, kelvin: f64) -> f64 {
+    let c = 299_792_458.0;
+    let h = 6.626_070_15e-34;
+    let kb = 1.380_649e-23;
+    let l = lambda * 1e-9;
+    2.0 * h * c * c / (l.powi(5) * ((h * c / (l * kb * kelvin)).exp() - 1.0))
+}
+
+// 黑体颜色（线性 sRGB），归一化为亮度 Y = 1；负分量截断为 0
+pub fn blackbody_rgb(kelvin: f64) -> Vec3 {
+    if kelvin <= 0.0 {
+        return Vec3::zero();
+    }
+    let mut xyz = Vec3::zero();
+    let mut lambda = LAMBDA_MIN;
+    while lambda <= LAMBDA_MAX {
+        xyz += cie_xyz(lambda) * planck(lambda, kelvin);
+        lambda += 5.0;
+    }
+    let rgb = xyz_to_rgb(xyz / xyz.y);
+    Vec3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+}
+
+// max(0, rgb(lambda)) 在 [LAMBDA_MIN, LAMBDA_MAX] 上的积分（数值积分得到，见测试）
+const WAVELENGTH_RGB_INTEGRAL: Vec3 = Vec3 {
+    x: 176.177,
+    y: 115.386,
+    z: 109.318,
+};
+
+// 单一波长对应的线性 sRGB 响应，负分量截断为 0
+pub fn wavelength_rgb(lambda: f64) -> Vec3 {
+    let rgb = xyz_to_rgb(cie_xyz(lambda));
+    Vec3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+}
+
+// 均匀采样一个波长，返回波长与 RGB 权重；权重对波长的期望为 (1, 1, 1)
+pub fn sample_wavelength(xi: f64) -> (f64, Vec3) {
+    let range = LAMBDA_MAX - LAMBDA_MIN;
+    let lambda = LAMBDA_MIN + xi * range;
+    let rgb = wavelength_rgb(lambda) * range;
+    let weight = Vec3::new(
+        rgb.x / WAVELENGTH_RGB_INTEGRAL.x,
+        rgb.y / WAVELENGTH_RGB_INTEGRAL.y,
+        rgb.z / WAVELENGTH_RGB_INTEGRAL.z,
+    );
+    (lambda, weight)
+}
+
+// 色散：折射率随波长变化，公式中的波长单位为微米
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    Cauchy { a: f64, b: f64 },              // n = a + b / λ²
+    Sellmeier { b: [f64; 3], c: [f64; 3] }, // n² = 1 + Σ b λ² / (λ² - c)
+}
+
+impl Dispersion {
+    #[allow(dead_code)]
+    pub fn bk7() -> Dispersion {
+        Dispersion::Sellmeier {
+            b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+            c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn fused_silica() -> Dispersion {
+        Dispersion::Sellmeier {
+            b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+            c: [0.004_679_148, 0.013_512_06, 97.934_00],
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn diamond() -> Dispersion {
+        Dispersion::Sellmeier {
+            b: [0.3306, 4.3356, 0.0],
+            c: [0.030_625, 0.011_236, 0.0],
+        }
+    }
+
+    // lambda 单位 nm
+    pub fn ior(&self, lambda: f64) -> f64 {
+        let l = lambda * 1e-3;
+        let l2 = l * l;
+        match *self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+// ---------- 光谱渲染 ----------
+
+// CIE 标准光源 D65 的相对功率分布，380..=780 nm，步长 10 nm（560 nm 处为 100）
+const D65_TABLE: [f64; 41] = [
+    49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+    115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100.0, 96.3342, 95.788,
+    88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778, 78.2842,
+    69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054, 63.3828,
+];
+
+// d65(lambda) * ȳ(lambda) 的积分，用于把胶片上的 Y 归一化到 RGB 模式的亮度
+const D65_Y: f64 = 105.692;
+
+pub fn d65(lambda: f64) -> f64 {
+    let t = clamp((lambda - LAMBDA_MIN) / 10.0, 0.0, 40.0);
+    let i = (t as usize).min(39);
+    let f = t - i as f64;
+    (D65_TABLE[i] * (1.0 - f) + D65_TABLE[i + 1] * f) / 100.0
+}
+
+// Smits (1999) 的 RGB -> 反射光谱基，380..720 nm 等分为 10 段，取各段中心
+const SMITS_MIN: f64 = 380.0;
+const SMITS_STEP: f64 = 34.0;
+const SMITS_WHITE: [f64; 10] = [1.0, 1.0, 0.9999, 0.9993, 0.9992, 0.9998, 1.0, 1.0, 1.0, 1.0];
+const SMITS_CYAN: [f64; 10] = [
+    0.971, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0, 0.0, 0.0,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0, 1.0, 0.9685, 0.2229, 0.0, 0.0458, 0.8369, 1.0, 1.0, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0, 0.1088, 0.6651, 1.0, 1.0, 0.9996, 0.9586, 0.9685, 0.984,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0, 0.0, 0.0, 0.0, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0, 0.0, 0.0273, 0.7937, 1.0, 0.9418, 0.1719, 0.0, 0.0, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0, 1.0, 0.8916, 0.3323, 0.0, 0.0, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+fn smits_basis(table: &[f64; 10], lambda: f64) -> f64 {
+    let t = clamp((lambda - SMITS_MIN) / SMITS_STEP - 0.5, 0.0, 9.0);
+    let i = (t as usize).min(8);
+    let f = t - i as f64;
+    table[i] * (1.0 - f) + table[i + 1] * f
+}
+
+// RGB 反照率在某一波长处的反射率（Smits 上采样）
+pub fn rgb_to_reflectance(c: Vec3, lambda: f64) -> f64 {
+    let b = |t: &[f64; 10]| smits_basis(t, lambda);
+    let (r, g, bl) = (c.x, c.y, c.z);
+    if r <= g && r <= bl {
+        let v = r * b(&SMITS_WHITE);
+        if g <= bl {
+            v + (g - r) * b(&SMITS_CYAN) + (bl - g) * b(&SMITS_BLUE)
+        } else {
+            v + (bl - r) * b(&SMITS_CYAN) + (g - bl) * b(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= bl {
+        let v = g * b(&SMITS_WHITE);
+        if r <= bl {
+            v + (r - g) * b(&SMITS_MAGENTA) + (bl - r) * b(&SMITS_BLUE)
+        } else {
+            v + (bl - g) * b(&SMITS_MAGENTA) + (r - bl) * b(&SMITS_RED)
+        }
+    } else {
+        let v = bl * b(&SMITS_WHITE);
+        if r <= g {
+            v + (r - bl) * b(&SMITS_YELLOW) + (g - r) * b(&SMITS_GREEN)
+        } else {
+            v + (g - bl) * b(&SMITS_YELLOW) + (r - g) * b(&SMITS_RED)
+        }
+    }
+}
+
+// 光谱模式下一条路径携带 3 个波长，与 Vec3 的三个分量一一对应
+
+// 主波长均匀采样，其余两个在可见光范围内等间隔旋转得到
+pub fn hero_wavelengths(xi: f64) -> Vec3 {
+    let range = LAMBDA_MAX - LAMBDA_MIN;
+    let at = |k: f64| LAMBDA_MIN + (xi + k / 3.0).fract() * range;
+    Vec3::new(at(0.0), at(1.0), at(2.0))
+}
+
+pub fn upsample_reflectance(c: Vec3, lambdas: Vec3) -> Vec3 {
+    Vec3::new(
+        rgb_to_reflectance(c, lambdas.x),
+        rgb_to_reflectance(c, lambdas.y),
+        rgb_to_reflectance(c, lambdas.z),
+    )
+}
+
+// RGB 发光颜色乘以 D65，使 (1, 1, 1) 的光源在输出中仍为白色
+pub fn upsample_illuminant(c: Vec3, lambdas: Vec3) -> Vec3 {
+    Vec3::new(
+        rgb_to_reflectance(c, lambdas.x) * d65(lambdas.x),
+        rgb_to_reflectance(c, lambdas.y) * d65(lambdas.y),
+        rgb_to_reflectance(c, lambdas.z) * d65(lambdas.z),
+    )
+}
+
+// 胶片：三个波长的辐亮度 -> XYZ -> 线性 sRGB
+pub fn spectrum_to_rgb(radiance: Vec3, lambdas: Vec3) -> Vec3 {
+    let xyz = cie_xyz(lambdas.x) * radiance.x
+        + cie_xyz(lambdas.y) * radiance.y
+        + cie_xyz(lambdas.z) * radiance.z;
+    xyz_to_rgb(xyz * ((LAMBDA_MAX - LAMBDA_MIN) / (3.0 * D65_Y)))
+}
+
+// 黑体光谱，归一化为与 blackbody_rgb 相同的亮度
+#[derive(Clone, Copy, Debug)]
+pub struct Blackbody {
+    pub kelvin: f64,
+    norm: f64,
+}
+
+impl Blackbody {
+    pub fn new(kelvin: f64) -> Blackbody {
+        let mut y = 0.0;
+        let mut lambda = LAMBDA_MIN;
+        while lambda <= LAMBDA_MAX {
+            y += cie_xyz(lambda).y * planck(lambda, kelvin) * 5.0;
+            lambda += 5.0;
+        }
+        let norm = if y > 0.0 { D65_Y / y } else { 0.0 };
+        Blackbody { kelvin, norm }
+    }
+
+    pub fn value(&self, lambda: f64) -> f64 {
+        planck(lambda, self.kelvin) * self.norm
+    }
+
+    pub fn sample(&self, lambdas: Vec3) -> Vec3 {
+        Vec3::new(
+            self.value(lambdas.x),
+            self.value(lambdas.y),
+            self.value(lambdas.z),
+        )
+    }
+}
+
+// 光源的光谱功率分布，亮度与 RGB 模式下的白色发光一致
+#[derive(Clone, Copy, Debug)]
+pub enum Illuminant {
+    Blackbody(Blackbody),
+    D65,
+}
+
+impl Illuminant {
+    pub fn sample(&self, lambdas: Vec3) -> Vec3 {
+        match self {
+            Illuminant::Blackbody(b) => b.sample(lambdas),
+            Illuminant::D65 => Vec3::new(d65(lambdas.x), d65(lambdas.y), d65(lambdas.z)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wavelength_rgb_integral() {
+        let mut sum = Vec3::zero();
+        let step = 0.1;
+        let mut lambda = LAMBDA_MIN + step / 2.0;
+        while lambda < LAMBDA_MAX {
+            sum += wavelength_rgb(lambda) * step;
+            lambda += step;
+        }
+        assert!((sum.x / WAVELENGTH_RGB_INTEGRAL.x - 1.0).abs() < 1e-4);
+        assert!((sum.y / WAVELENGTH_RGB_INTEGRAL.y - 1.0).abs() < 1e-4);
+        assert!((sum.z / WAVELENGTH_RGB_INTEGRAL.z - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_d65_white_film() {
+        // D65 照明下的白色在胶片上还原为 (1, 1, 1)
+        let n = 4000;
+        let mut sum = Vec3::zero();
+        for i in 0..n {
+            let ls = hero_wavelengths((i as f64 + 0.5) / n as f64);
+            sum += spectrum_to_rgb(upsample_illuminant(Vec3::ones(), ls), ls);
+        }
+        let rgb = sum / n as f64;
+        assert!((rgb.x - 1.0).abs() < 1e-2);
+        assert!((rgb.y - 1.0).abs() < 1e-2);
+        assert!((rgb.z - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_bk7_d_line() {
+        assert!((Dispersion::bk7().ior(587.6) - 1.5168).abs() < 1e-3);
+        assert!((Dispersion::diamond().ior(587.6) - 2.417).abs() < 5e-3);
+    }
+}
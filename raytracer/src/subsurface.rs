@@ -0,0 +1,182 @@
+use crate::aabb::AABB;
+use crate::clamp;
+use crate::hittable::{Hitrecord, Object};
+use crate::material::{
+    hg_direction, reflect, refract, sample_hg_cos, spectral_reflectance, Material, Scatter,
+};
+use crate::microfacet::fresnel_dielectric;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::sync::Arc;
+
+// 随机游走次表面散射：光线折射进入边界后在内部做体积散射，直到从某处折射离开
+#[derive(Clone)]
+pub struct Subsurface {
+    pub boundary: Arc<dyn Object>,
+    pub walk: Arc<RandomWalk>,
+}
+
+impl Subsurface {
+    // albedo 为多次散射后的表面颜色，radius 为各通道的平均自由程（世界单位）
+    #[allow(dead_code)]
+    pub fn new(
+        boundary: Arc<dyn Object>,
+        albedo: Arc<dyn Texture>,
+        radius: Arc<dyn Texture>,
+        ior: f64,
+    ) -> Subsurface {
+        Subsurface::new_with_phase(boundary, albedo, radius, ior, 0.0)
+    }
+
+    // g 为内部 Henyey-Greenstein 相函数的各向异性参数
+    pub fn new_with_phase(
+        boundary: Arc<dyn Object>,
+        albedo: Arc<dyn Texture>,
+        radius: Arc<dyn Texture>,
+        ior: f64,
+        g: f64,
+    ) -> Subsurface {
+        let walk = Arc::new(RandomWalk {
+            boundary: boundary.clone(),
+            albedo,
+            radius,
+            ior,
+            g,
+            max_bounces: 256,
+        });
+        Subsurface { boundary, walk }
+    }
+}
+
+impl Object for Subsurface {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        let mut rec = self.boundary.hit(ray, t_min, t_max)?;
+        rec.mat_ptr = self.walk.clone();
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
+// 由多次散射反照率反推单次散射反照率（van de Hulst 近似）
+pub fn single_scatter_albedo(a: f64) -> f64 {
+    let a = clamp(a, 0.0, 0.999);
+    let s = 4.097_12 + 4.208_63 * a - (9.592_17 + 41.680_8 * a + 17.712_6 * a * a).sqrt();
+    1.0 - s * s
+}
+
+fn channel(c: Vec3, i: usize) -> f64 {
+    match i {
+        0 => c.x,
+        1 => c.y,
+        _ => c.z,
+    }
+}
+
+fn exp3(c: Vec3) -> Vec3 {
+    Vec3::new(c.x.exp(), c.y.exp(), c.z.exp())
+}
+
+// 按当前通量的比例选择采样通道，返回通道序号与各通道的概率
+fn pick_channel(weight: Vec3, xi: f64) -> (usize, Vec3) {
+    let sum = weight.x + weight.y + weight.z;
+    let q = if sum > 0.0 {
+        weight / sum
+    } else {
+        Vec3::ones() / 3.0
+    };
+    let c = if xi < q.x {
+        0
+    } else if xi < q.x + q.y {
+        1
+    } else {
+        2
+    };
+    (c, q)
+}
+
+#[derive(Clone)]
+pub struct RandomWalk {
+    pub boundary: Arc<dyn Object>,
+    pub albedo: Arc<dyn Texture>,
+    pub radius: Arc<dyn Texture>,
+    pub ior: f64,
+    pub g: f64,
+    pub max_bounces: usize,
+}
+
+impl Material for RandomWalk {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let unit = _r_in.drc.unit();
+        let cos_i = -(unit * rec.n);
+
+        // 光滑界面：按菲涅尔项选择镜面反射或折射进入
+        if _rng.gen::<f64>() < fresnel_dielectric(cos_i, self.ior) {
+            let sed = Ray::new(rec.p, reflect(unit, rec.n), _r_in.tm);
+            return Some(Scatter::new(Vec3::ones(), sed));
+        }
+
+        // 散射参数在入射点处求值，光谱模式下上采样到光线的三个波长
+        let albedo = spectral_reflectance(self.albedo.value_at(rec), _r_in);
+        let radius = spectral_reflectance(self.radius.value_at(rec), _r_in);
+        let sigma_t = Vec3::new(
+            1.0 / radius.x.max(1e-6),
+            1.0 / radius.y.max(1e-6),
+            1.0 / radius.z.max(1e-6),
+        );
+        let sigma_s = Vec3::elemul(
+            sigma_t,
+            Vec3::new(
+                single_scatter_albedo(albedo.x),
+                single_scatter_albedo(albedo.y),
+                single_scatter_albedo(albedo.z),
+            ),
+        );
+
+        let mut p = rec.p;
+        let mut drc = refract(unit, rec.n, 1.0 / self.ior).unit();
+        let mut weight = Vec3::ones();
+
+        for _ in 0..self.max_bounces {
+            let exit = self
+                .boundary
+                .hit(&Ray::new(p, drc, _r_in.tm), 1e-4, f64::INFINITY)?;
+
+            // 按通量选一个通道采样距离，pdf 取各通道 pdf 的加权和（单样本 MIS）
+            let (c, q) = pick_channel(weight, _rng.gen::<f64>());
+            let t = -(1.0 - _rng.gen::<f64>()).ln() / channel(sigma_t, c);
+
+            if t < exit.t {
+                let tr = exp3(-sigma_t * t);
+                let pdf = q * Vec3::elemul(sigma_t, tr);
+                weight = Vec3::elemul(weight, Vec3::elemul(sigma_s, tr)) / pdf;
+                p += drc * t;
+                let cos_theta = sample_hg_cos(self.g, _rng.gen::<f64>());
+                drc = hg_direction(drc, cos_theta, _rng).unit();
+                continue;
+            }
+
+            // 到达边界：折射离开，或发生内部反射后继续游走
+            let tr = exp3(-sigma_t * exit.t);
+            weight = Vec3::elemul(weight, tr) / (q * tr);
+            p = exit.p;
+            let cos_o = -(drc * exit.n);
+            if _rng.gen::<f64>() < fresnel_dielectric(cos_o, 1.0 / self.ior) {
+                drc = reflect(drc, exit.n).unit();
+            } else {
+                let out = refract(drc, exit.n, self.ior);
+                return Some(Scatter::new_spectral(weight, Ray::new(p, out, _r_in.tm)));
+            }
+        }
+        None
+    }
+
+    fn emitted(&self, _rec: &Hitrecord) -> Vec3 {
+        Vec3::zero()
+    }
+}
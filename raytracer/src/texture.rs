@@ -1,14 +1,34 @@
 use crate::clamp;
+use crate::hittable::Hitrecord;
+use crate::perlin::Perlin;
 use crate::vec3::*;
-// use crate::Perlin;
+use image::codecs::hdr::HdrDecoder;
 use image::GenericImageView;
-use std::path::Path;
-use std::sync::Arc;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 pub trait Texture: Sync + Send {
     fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+
+    // 在交点处取值，可利用 rec 中 uv 的屏幕空间导数做抗锯齿（图片纹理据此选择 mip 层级）
+    fn value_at(&self, rec: &Hitrecord) -> Vec3 {
+        self.value(rec.u, rec.v, rec.p)
+    }
+
+    // 不透明度，默认完全不透明
+    fn alpha(&self, _u: f64, _v: f64, _p: Vec3) -> f64 {
+        1.0
+    }
 }
 
+#[allow(dead_code)]
 #[derive(Clone)]
 pub struct CheckerT {
     pub odd: Arc<dyn Texture>,
@@ -63,13 +83,14 @@ impl Texture for Solid {
     }
 }
 
-/* #[derive(Clone)]
+#[derive(Clone)]
 pub struct Noise {
     noise: Perlin,
     scale: f64,
 }
 
 impl Noise {
+    #[allow(dead_code)]
     pub fn new(noise: Perlin, scale: f64) -> Noise {
         Noise { noise, scale }
     }
@@ -79,45 +100,661 @@ impl Texture for Noise {
     fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
         Vec3::ones() * 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turb(p, 7)).sin())
     }
-} */
+}
+
+// 湍流，可直接作为非均匀介质的密度场
+#[derive(Clone)]
+pub struct Turbulence {
+    noise: Perlin,
+    scale: f64,
+    depth: i32,
+}
+
+impl Turbulence {
+    pub fn new(noise: Perlin, scale: f64, depth: i32) -> Turbulence {
+        Turbulence {
+            noise,
+            scale,
+            depth,
+        }
+    }
+}
+
+impl Texture for Turbulence {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.noise.turb(p * self.scale, self.depth)
+    }
+}
+
+// 贴图过滤方式
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    Bicubic, // Catmull-Rom
+}
+
+// 超出 [0, 1] 的 uv 如何取值
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    Mirror,
+    Clamp,
+    Border(Vec3, f64), // 边界外取固定颜色与不透明度
+}
+
+// 把整数像素坐标映射回 [0, n)，Border 模式下越界返回 None
+fn wrap_index(i: i64, n: i64, wrap: Wrap) -> Option<i64> {
+    match wrap {
+        Wrap::Repeat => Some(i.rem_euclid(n)),
+        Wrap::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            Some(if m < n { m } else { 2 * n - 1 - m })
+        }
+        Wrap::Clamp => Some(i.max(0).min(n - 1)),
+        Wrap::Border(..) => {
+            if i >= 0 && i < n {
+                Some(i)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Catmull-Rom 权重，t 为采样点到左侧第二个像素中心的小数部分
+fn cubic_weights(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+// 每个贴图实例的 uv 变换：先绕贴图中心旋转（弧度），再缩放、平移
+#[derive(Clone, Copy, Debug)]
+pub struct UvTransform {
+    pub scale: (f64, f64),
+    pub offset: (f64, f64),
+    pub rotation: f64,
+}
+
+impl UvTransform {
+    pub fn new(scale: (f64, f64), offset: (f64, f64), rotation: f64) -> UvTransform {
+        UvTransform {
+            scale,
+            offset,
+            rotation,
+        }
+    }
+
+    pub fn identity() -> UvTransform {
+        UvTransform::new((1.0, 1.0), (0.0, 0.0), 0.0)
+    }
+
+    // 只作用线性部分，用于变换 uv 的导数
+    pub fn apply_vector(&self, du: f64, dv: f64) -> (f64, f64) {
+        let (s, c) = self.rotation.sin_cos();
+        (
+            (c * du - s * dv) * self.scale.0,
+            (s * du + c * dv) * self.scale.1,
+        )
+    }
+
+    pub fn apply(&self, u: f64, v: f64) -> (f64, f64) {
+        let (s, c) = self.rotation.sin_cos();
+        let (x, y) = (u - 0.5, v - 0.5);
+        let (x, y) = (c * x - s * y + 0.5, s * x + c * y + 0.5);
+        (
+            x * self.scale.0 + self.offset.0,
+            y * self.scale.1 + self.offset.1,
+        )
+    }
+}
+
+// mip 金字塔中的一层，按行存放 RGBA（0~1）
+#[derive(Clone)]
+pub struct MipLevel {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<[f32; 4]>,
+}
+
+impl MipLevel {
+    // 8 位与 16 位图片统一按 16 位读入，颜色贴图在此解码到线性空间，alpha 始终是线性的
+    fn from_image(img: &image::DynamicImage, color_space: ColorSpace) -> MipLevel {
+        let (width, height) = img.dimensions();
+        let sc = 1.0 / 65535.0;
+        let decode = |c: u16| {
+            let c = c as f64 * sc;
+            match color_space {
+                ColorSpace::Srgb => srgb_to_linear(c) as f32,
+                ColorSpace::Linear => c as f32,
+            }
+        };
+        let data = img
+            .to_rgba16()
+            .pixels()
+            .map(|p| {
+                [
+                    decode(p[0]),
+                    decode(p[1]),
+                    decode(p[2]),
+                    p[3] as f32 * sc as f32,
+                ]
+            })
+            .collect();
+        MipLevel {
+            width,
+            height,
+            data,
+        }
+    }
+
+    // Radiance .hdr：浮点线性辐射度，没有 alpha
+    fn from_hdr(path: &Path) -> Result<MipLevel, TextureError> {
+        let file = File::open(path).map_err(|source| TextureError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let decode_err = |source| TextureError::Decode {
+            path: path.to_path_buf(),
+            source,
+        };
+        let decoder = HdrDecoder::new(BufReader::new(file)).map_err(decode_err)?;
+        let meta = decoder.metadata();
+        let data = decoder
+            .read_image_hdr()
+            .map_err(decode_err)?
+            .iter()
+            .map(|p| [p[0], p[1], p[2], 1.0])
+            .collect();
+        Ok(MipLevel {
+            width: meta.width,
+            height: meta.height,
+            data,
+        })
+    }
+
+    fn get(&self, x: u32, y: u32) -> [f32; 4] {
+        self.data[(y * self.width + x) as usize]
+    }
+
+    // 盒式滤波缩小一半（取整），两个轴分别按 box_taps 取权重
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut c = [0.0; 4];
+                for &(ty, wy) in box_taps(self.height, y).iter() {
+                    for &(tx, wx) in box_taps(self.width, x).iter() {
+                        let t = self.get(tx, ty);
+                        for ch in 0..4 {
+                            c[ch] += wx * wy * t[ch];
+                        }
+                    }
+                }
+                data.push(c);
+            }
+        }
+        MipLevel {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+// 缩小一半时输出第 i 个像素在某一轴上的源像素与权重。偶数尺寸取相邻两个像素各一半；
+// 奇数尺寸 n = 2m + 1 时每个输出像素覆盖 n / m 个源像素，用 3 个像素按覆盖长度加权，
+// 使每个源像素的总贡献相同（长度为 1 时原样保留）
+fn box_taps(n: u32, i: u32) -> [(u32, f32); 3] {
+    if n == 1 {
+        [(0, 1.0), (0, 0.0), (0, 0.0)]
+    } else if n % 2 == 1 {
+        let m = (n / 2) as f32;
+        let k = i as f32;
+        let n = n as f32;
+        [
+            (2 * i, (m - k) / n),
+            (2 * i + 1, m / n),
+            (2 * i + 2, (k + 1.0) / n),
+        ]
+    } else {
+        [(2 * i, 0.5), (2 * i + 1, 0.5), (0, 0.0)]
+    }
+}
+
+// 贴图像素的编码：颜色贴图为 sRGB，法线、粗糙度等数据贴图按原值读取
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    NotFound {
+        path: PathBuf,
+        tried: Vec<PathBuf>,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Decode {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::NotFound { path, tried } => {
+                write!(f, "texture {} not found (tried", path.display())?;
+                for t in tried {
+                    write!(f, " {}", t.display())?;
+                }
+                write!(f, ")")
+            }
+            TextureError::Io { path, source } => {
+                write!(f, "failed to read texture {}: {}", path.display(), source)
+            }
+            TextureError::Decode { path, source } => {
+                write!(f, "failed to decode texture {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::NotFound { .. } => None,
+            TextureError::Io { source, .. } => Some(source),
+            TextureError::Decode { source, .. } => Some(source),
+        }
+    }
+}
+
+type MipChain = Arc<Vec<MipLevel>>;
+
+lazy_static! {
+    // 已解码的贴图，按（规范化路径，颜色空间）共享，同一文件只解码一次
+    static ref TEXTURE_CACHE: Mutex<HashMap<(PathBuf, ColorSpace), MipChain>> =
+        Mutex::new(HashMap::new());
+    static ref TEXTURE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+// 设置相对路径优先查找的目录（场景目录），由 main 按命令行参数设置
+pub fn set_texture_dir(dir: &Path) {
+    *TEXTURE_DIR.lock().unwrap() = Some(dir.to_path_buf());
+}
+
+// 运行时的查找顺序：场景目录、当前目录、可执行文件所在目录及其上两级
+// （target/release/raytracer 向上两级即工作区根目录）
+fn texture_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = TEXTURE_DIR.lock().unwrap().iter().cloned().collect();
+    dirs.push(PathBuf::new());
+    if let Some(exe_dir) = env::current_exe().ok().as_ref().and_then(|e| e.parent()) {
+        dirs.extend(exe_dir.ancestors().take(3).map(Path::to_path_buf));
+    }
+    dirs
+}
+
+// 绝对路径直接使用，相对路径依次在 texture_dirs 中查找
+pub fn resolve_path(path: &Path) -> Result<PathBuf, TextureError> {
+    let tried: Vec<PathBuf> = if path.is_absolute() {
+        vec![path.to_path_buf()]
+    } else {
+        texture_dirs().iter().map(|d| d.join(path)).collect()
+    };
+    for p in tried.iter() {
+        if p.is_file() {
+            return fs::canonicalize(p).map_err(|source| TextureError::Io {
+                path: p.clone(),
+                source,
+            });
+        }
+    }
+    Err(TextureError::NotFound {
+        path: path.to_path_buf(),
+        tried,
+    })
+}
+
+// 解码并建立 mip 金字塔，结果放入缓存
+fn load_mip_chain(path: &Path, color_space: ColorSpace) -> Result<MipChain, TextureError> {
+    let path = resolve_path(path)?;
+    let key = (path.clone(), color_space);
+    if let Some(levels) = TEXTURE_CACHE.lock().unwrap().get(&key) {
+        return Ok(levels.clone());
+    }
+
+    // 解码时不持有锁，其他线程可以同时加载别的贴图
+
+    let is_hdr = matches!(path.extension(), Some(e) if e.eq_ignore_ascii_case("hdr"));
+    let base = if is_hdr {
+        MipLevel::from_hdr(&path)?
+    } else {
+        let img = image::open(&path).map_err(|source| TextureError::Decode {
+            path: path.clone(),
+            source,
+        })?;
+        MipLevel::from_image(&img, color_space)
+    };
+    let mut levels = vec![base];
+    loop {
+        let last = &levels[levels.len() - 1];
+        if last.width == 1 && last.height == 1 {
+            break;
+        }
+        let next = last.downsample();
+        levels.push(next);
+    }
+    // 同一文件被并发加载时保留先放入缓存的结果，保证实例之间共享
+    let mut cache = TEXTURE_CACHE.lock().unwrap();
+    Ok(cache.entry(key).or_insert_with(|| Arc::new(levels)).clone())
+}
 
 #[derive(Clone)]
 pub struct ImageTexture {
-    pub img: image::DynamicImage,
+    pub levels: MipChain, // levels[0] 为原图，逐层缩小一半直到 1x1，同一文件的实例共享
     pub nx: u32,
     pub ny: u32,
+    pub filter: Filter,
+    pub wrap: Wrap,
+    pub transform: UvTransform,
+}
+
+impl ImageTexture {
+    // 与原来的行为一致：最近邻取样，其他过滤方式通过 new_with_sampler 指定
+    pub fn new(path: &Path) -> Result<Self, TextureError> {
+        ImageTexture::new_with_sampler(path, Filter::Nearest, Wrap::Clamp, ColorSpace::Srgb)
+    }
+
+    // 数据贴图（法线、粗糙度、高度……）：不做 sRGB 解码
+    #[allow(dead_code)]
+    pub fn new_linear(path: &Path) -> Result<Self, TextureError> {
+        ImageTexture::new_with_sampler(path, Filter::Bilinear, Wrap::Clamp, ColorSpace::Linear)
+    }
+
+    // .hdr 文件总是按线性浮点读入，color_space 只对 8/16 位图片生效
+    pub fn new_with_sampler(
+        path: &Path,
+        filter: Filter,
+        wrap: Wrap,
+        color_space: ColorSpace,
+    ) -> Result<Self, TextureError> {
+        let levels = load_mip_chain(path, color_space)?;
+        Ok(Self {
+            nx: levels[0].width,
+            ny: levels[0].height,
+            levels,
+            filter,
+            wrap,
+            transform: UvTransform::identity(),
+        })
+    }
+}
+
+// 场景中使用：贴图缺失时打印原因并以纯色代替，渲染不中断
+pub fn image_or_solid(path: &Path, fallback: Vec3) -> Arc<dyn Texture> {
+    match ImageTexture::new(path) {
+        Ok(t) => Arc::new(t),
+        Err(e) => {
+            eprintln!("warning: {}", e);
+            Arc::new(Solid::new(fallback))
+        }
+    }
 }
 
 impl ImageTexture {
-    pub fn new(path: &Path) -> Self {
-        let img = image::open(path).unwrap();
-        Self {
-            img: img.clone(),
-            nx: img.dimensions().0,
-            ny: img.dimensions().1,
+    // 取某一层整数坐标处的像素（RGBA，0~1），先按 wrap 处理越界
+    fn texel(&self, level: usize, i: i64, j: i64) -> [f64; 4] {
+        let mip = &self.levels[level];
+        let x = wrap_index(i, mip.width as i64, self.wrap);
+        let y = wrap_index(j, mip.height as i64, self.wrap);
+        match (x, y) {
+            (Some(x), Some(y)) => {
+                let p = mip.get(x as u32, y as u32);
+                [p[0] as f64, p[1] as f64, p[2] as f64, p[3] as f64]
+            }
+            _ => match self.wrap {
+                Wrap::Border(c, a) => [c.x, c.y, c.z, a],
+                _ => [0.0; 4],
+            },
         }
     }
+
+    // 在某一层上按 filter 取值，uv 已经过变换
+    fn sample_level(&self, level: usize, u: f64, v: f64) -> [f64; 4] {
+        let mip = &self.levels[level];
+        // 连续像素坐标，像素中心位于整数 + 0.5，图片的第 0 行对应 v = 1
+        let x = u * mip.width as f64;
+        let y = (1.0 - v) * mip.height as f64;
+        match self.filter {
+            Filter::Nearest => self.texel(level, x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i, j) = (x.floor(), y.floor());
+                let (tx, ty) = (x - i, y - j);
+                let wx = [1.0 - tx, tx];
+                let wy = [1.0 - ty, ty];
+                self.weighted(level, i as i64, j as i64, &wx, &wy)
+            }
+            Filter::Bicubic => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i, j) = (x.floor(), y.floor());
+                let wx = cubic_weights(x - i);
+                let wy = cubic_weights(y - j);
+                let mut c = self.weighted(level, i as i64 - 1, j as i64 - 1, &wx, &wy);
+                // Catmull-Rom 在锐利边缘处会过冲：HDR 颜色没有上限，只截掉负值，alpha 限制在 [0, 1]
+                for ch in c[..3].iter_mut() {
+                    *ch = ch.max(0.0);
+                }
+                c[3] = clamp(c[3], 0.0, 1.0);
+                c
+            }
+        }
+    }
+
+    // 以 (i0, j0) 为左上角，按可分离权重累加一块像素
+    fn weighted(&self, level: usize, i0: i64, j0: i64, wx: &[f64], wy: &[f64]) -> [f64; 4] {
+        let mut c = [0.0; 4];
+        for (dj, ky) in wy.iter().enumerate() {
+            for (di, kx) in wx.iter().enumerate() {
+                let t = self.texel(level, i0 + di as i64, j0 + dj as i64);
+                for ch in 0..4 {
+                    c[ch] += t[ch] * kx * ky;
+                }
+            }
+        }
+        c
+    }
+
+    fn sample(&self, u: f64, v: f64) -> [f64; 4] {
+        let (u, v) = self.transform.apply(u, v);
+        self.sample_level(0, u, v)
+    }
+
+    // 三线性 mip 查找：按覆盖范围较长的一边（以原图像素计）选择层级，相邻两层之间线性插值
+    fn sample_footprint(&self, rec: &Hitrecord) -> [f64; 4] {
+        let (u, v) = self.transform.apply(rec.u, rec.v);
+        let (dux, dvx) = self.transform.apply_vector(rec.dudx, rec.dvdx);
+        let (duy, dvy) = self.transform.apply_vector(rec.dudy, rec.dvdy);
+        let (w, h) = (self.nx as f64, self.ny as f64);
+        let lx = (dux * w).hypot(dvx * h);
+        let ly = (duy * w).hypot(dvy * h);
+        let width = lx.max(ly);
+
+        let max_level = (self.levels.len() - 1) as f64;
+        if width.is_nan() || width <= 1.0 {
+            return self.sample_level(0, u, v);
+        }
+        let lod = width.log2().min(max_level);
+        let l0 = lod.floor();
+        let t = lod - l0;
+        let a = self.sample_level(l0 as usize, u, v);
+        if t == 0.0 {
+            return a;
+        }
+        let b = self.sample_level(l0 as usize + 1, u, v);
+        let mut c = [0.0; 4];
+        for ch in 0..4 {
+            c[ch] = a[ch] * (1.0 - t) + b[ch] * t;
+        }
+        c
+    }
 }
 
 impl Texture for ImageTexture {
     fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
-        let u = clamp(u, 0.0, 1.0);
-        let v = 1.0 - clamp(v, 0.0, 1.0);
-        let mut i = (u * self.nx as f64) as u32;
-        let mut j = (v * self.ny as f64 - 0.001) as u32;
-
-        if i >= self.nx {
-            i = self.nx - 1;
-        }
-        if j >= self.ny {
-            j = self.ny - 1;
-        }
-        let sc: f64 = 1.0 / 255.0;
-        let pixel = self.img.get_pixel(i as u32, j as u32);
-        Vec3::new(
-            pixel[0] as f64 * sc,
-            pixel[1] as f64 * sc,
-            pixel[2] as f64 * sc,
-        )
+        let c = self.sample(u, v);
+        Vec3::new(c[0], c[1], c[2])
+    }
+
+    fn value_at(&self, rec: &Hitrecord) -> Vec3 {
+        let c = self.sample_footprint(rec);
+        Vec3::new(c[0], c[1], c[2])
+    }
+
+    // 没有 alpha 通道的图片读出的 alpha 恒为 1
+    fn alpha(&self, u: f64, v: f64, _p: Vec3) -> f64 {
+        self.sample(u, v)[3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_index() {
+        assert_eq!(wrap_index(-1, 4, Wrap::Repeat), Some(3));
+        assert_eq!(wrap_index(9, 4, Wrap::Repeat), Some(1));
+        assert_eq!(wrap_index(-1, 4, Wrap::Mirror), Some(0));
+        assert_eq!(wrap_index(5, 4, Wrap::Mirror), Some(2));
+        assert_eq!(wrap_index(-3, 4, Wrap::Clamp), Some(0));
+        assert_eq!(wrap_index(4, 4, Wrap::Border(Vec3::zero(), 0.0)), None);
+    }
+
+    #[test]
+    fn test_cubic_weights() {
+        // 权重之和为 1，落在像素中心时只取该像素
+        let w = cubic_weights(0.3);
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert_eq!(cubic_weights(0.0), [0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for &c in [0.0, 0.002, 0.04, 0.2, 0.5, 1.0].iter() {
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-12);
+        }
+        // 中灰 sRGB 128 约为线性 0.216
+        assert!((srgb_to_linear(128.0 / 255.0) - 0.2158).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_missing_texture() {
+        let err = ImageTexture::new(Path::new("no_such_texture.png"))
+            .err()
+            .unwrap();
+        assert!(matches!(err, TextureError::NotFound { .. }));
+        assert!(err.to_string().contains("no_such_texture.png"));
+    }
+
+    #[test]
+    fn test_cache_shares_decoded_image() {
+        let name = format!("raytracer_cache_test_{}.png", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        image::RgbImage::new(4, 2).save(&path).unwrap();
+        let a = ImageTexture::new(&path).unwrap();
+        let b = ImageTexture::new(&path).unwrap();
+        let c = ImageTexture::new_linear(&path).unwrap();
+        assert!(Arc::ptr_eq(&a.levels, &b.levels));
+        assert!(!Arc::ptr_eq(&a.levels, &c.levels));
+        assert_eq!(a.levels.len(), 3);
+        assert_eq!(a.filter, Filter::Nearest);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bicubic_alpha_clamped() {
+        // 0 -> 1 的阶跃在 1 一侧过冲，颜色保留过冲，alpha 不超过 1
+        let texel = |v: f32| [v, v, v, v];
+        let level = MipLevel {
+            width: 5,
+            height: 1,
+            data: [0.0, 0.0, 1.0, 1.0, 1.0]
+                .iter()
+                .map(|&v| texel(v))
+                .collect(),
+        };
+        let tex = ImageTexture {
+            levels: Arc::new(vec![level]),
+            nx: 5,
+            ny: 1,
+            filter: Filter::Bicubic,
+            wrap: Wrap::Clamp,
+            transform: UvTransform::identity(),
+        };
+        let c = tex.sample_level(0, 2.75 / 5.0, 0.5);
+        assert!(c[0] > 1.0);
+        assert_eq!(c[3], 1.0);
+    }
+
+    #[test]
+    fn test_downsample() {
+        // 奇数尺寸缩小后取整，最后一列也参与平均
+        let texel = |v: f32| [v, 0.0, 0.0, 1.0];
+        let level = MipLevel {
+            width: 3,
+            height: 2,
+            data: [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+                .iter()
+                .map(|&v| texel(v))
+                .collect(),
+        };
+        let half = level.downsample();
+        assert_eq!((half.width, half.height), (1, 1));
+        assert!((half.data[0][0] - 3.5).abs() < 1e-6);
+        assert!((half.data[0][3] - 1.0).abs() < 1e-6);
+
+        // 5 -> 2：3 抽头加权，平均值保持不变
+        let row = MipLevel {
+            width: 5,
+            height: 1,
+            data: (0..5).map(|v| texel(v as f32)).collect(),
+        };
+        let half = row.downsample();
+        assert_eq!((half.width, half.height), (2, 1));
+        assert!((half.data[0][0] - 0.8).abs() < 1e-6);
+        assert!((half.data[1][0] - 3.2).abs() < 1e-6);
     }
 }
@@ -0,0 +1,288 @@
+use crate::clamp;
+use crate::hittable::Hitrecord;
+use crate::material::{Material, Scatter};
+use crate::ray::Ray;
+use crate::spectrum::{cie_xyz, xyz_to_rgb, LAMBDA_MAX, LAMBDA_MIN};
+use crate::texture::{Solid, Texture};
+use crate::vec3::Vec3;
+use rand::rngs::ThreadRng;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// 薄膜干涉：在基底上覆盖一层厚度为纳米量级的透明膜，按波长计算 Airy 反射率再积分到 RGB
+
+const LAMBDA_STEP: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn real(re: f64) -> Complex {
+        Complex::new(re, 0.0)
+    }
+
+    fn add(self, o: Complex) -> Complex {
+        Complex::new(self.re + o.re, self.im + o.im)
+    }
+
+    fn sub(self, o: Complex) -> Complex {
+        Complex::new(self.re - o.re, self.im - o.im)
+    }
+
+    fn mul(self, o: Complex) -> Complex {
+        Complex::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+
+    fn div(self, o: Complex) -> Complex {
+        let d = o.re * o.re + o.im * o.im;
+        Complex::new(
+            (self.re * o.re + self.im * o.im) / d,
+            (self.im * o.re - self.re * o.im) / d,
+        )
+    }
+
+    fn norm2(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // 主值平方根（实部非负）
+    fn sqrt(self) -> Complex {
+        let r = self.norm2().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn exp(self) -> Complex {
+        let m = self.re.exp();
+        Complex::new(m * self.im.cos(), m * self.im.sin())
+    }
+}
+
+// 三层结构 n1 | 膜 n2（厚度 d，nm）| 基底 n3 的反射率（非偏振），lambda 单位 nm
+fn airy_reflectance(cos1: f64, lambda: f64, n1: f64, n2: f64, d: f64, n3: Complex) -> f64 {
+    let one = Complex::real(1.0);
+    let sin1_2 = (1.0 - cos1 * cos1).max(0.0);
+    let c1 = Complex::real(cos1);
+    let (n1c, n2c) = (Complex::real(n1), Complex::real(n2));
+    let cos2 = Complex::real(1.0 - sin1_2 * (n1 / n2) * (n1 / n2)).sqrt();
+    let s3 = n1c.div(n3);
+    let cos3 = one.sub(s3.mul(s3).mul(Complex::real(sin1_2))).sqrt();
+
+    let fresnel = |na: Complex, ca: Complex, nb: Complex, cb: Complex| {
+        let s = na.mul(ca).sub(nb.mul(cb)).div(na.mul(ca).add(nb.mul(cb)));
+        let p = nb.mul(ca).sub(na.mul(cb)).div(nb.mul(ca).add(na.mul(cb)));
+        (s, p)
+    };
+    let (r12s, r12p) = fresnel(n1c, c1, n2c, cos2);
+    let (r23s, r23p) = fresnel(n2c, cos2, n3, cos3);
+
+    // 膜内往返一次的相位差
+    let phase = cos2.mul(Complex::real(4.0 * PI * n2 * d / lambda));
+    let e = Complex::new(-phase.im, phase.re).exp();
+    let airy = |r12: Complex, r23: Complex| {
+        let num = r12.add(r23.mul(e));
+        let den = one.add(r12.mul(r23).mul(e));
+        num.div(den).norm2()
+    };
+    (0.5 * (airy(r12s, r23s) + airy(r12p, r23p))).min(1.0)
+}
+
+// 按 R、G、B 的代表波长对基底折射率做分段线性插值
+fn channel_lerp(c: Vec3, lambda: f64) -> f64 {
+    if lambda >= 650.0 {
+        c.x
+    } else if lambda >= 550.0 {
+        let t = (lambda - 550.0) / 100.0;
+        c.y + (c.x - c.y) * t
+    } else if lambda >= 450.0 {
+        let t = (lambda - 450.0) / 100.0;
+        c.z + (c.y - c.z) * t
+    } else {
+        c.z
+    }
+}
+
+// 基底的复折射率 eta + ik，按 RGB 代表波长插值
+fn substrate(eta: Vec3, k: Vec3, lambda: f64) -> Complex {
+    Complex::new(channel_lerp(eta, lambda), channel_lerp(k, lambda))
+}
+
+// 薄膜反射率在可见光范围内积分后的线性 sRGB 值；恒定反射率 R 对应 (R, R, R)
+pub fn film_reflectance(
+    cos_i: f64,
+    n1: f64,
+    film_ior: f64,
+    thickness: f64,
+    eta: Vec3,
+    k: Vec3,
+) -> Vec3 {
+    let mut xyz = Vec3::zero();
+    let mut white = Vec3::zero();
+    let mut lambda = LAMBDA_MIN;
+    while lambda <= LAMBDA_MAX {
+        let cmf = cie_xyz(lambda);
+        let n3 = substrate(eta, k, lambda);
+        let r = airy_reflectance(cos_i, lambda, n1, film_ior, thickness, n3);
+        xyz += cmf * r;
+        white += cmf;
+        lambda += LAMBDA_STEP;
+    }
+    let rgb = xyz_to_rgb(xyz);
+    let w = xyz_to_rgb(white);
+    Vec3::new(
+        clamp(rgb.x / w.x, 0.0, 1.0),
+        clamp(rgb.y / w.y, 0.0, 1.0),
+        clamp(rgb.z / w.z, 0.0, 1.0),
+    )
+}
+
+// 光谱模式下直接在光线携带的三个波长处求反射率
+pub fn film_spectrum(
+    cos_i: f64,
+    n1: f64,
+    film_ior: f64,
+    thickness: f64,
+    eta: Vec3,
+    k: Vec3,
+    lambdas: Vec3,
+) -> Vec3 {
+    let r = |lambda: f64| {
+        let n3 = substrate(eta, k, lambda);
+        airy_reflectance(cos_i, lambda, n1, film_ior, thickness, n3)
+    };
+    Vec3::new(r(lambdas.x), r(lambdas.y), r(lambdas.z))
+}
+
+#[derive(Clone)]
+pub struct ThinFilm {
+    // 覆盖在光滑基底上的干涉膜：肥皂泡、油膜、阳极氧化金属
+    // 膜的 Airy 反射率代替基底自身的界面反射，基底的折射率、复折射率和散射方向都由基底给出
+    pub base: Arc<dyn Material>,
+    pub thickness: Arc<dyn Texture>, // 膜厚（nm），取纹理的 x 分量
+    pub film_ior: f64,
+}
+
+impl ThinFilm {
+    // base 应是有明确界面的材质（Dielectric、RoughDielectric、Conductor），肥皂泡取折射率 1.0 的 Dielectric
+    pub fn new(base: Arc<dyn Material>, thickness: Arc<dyn Texture>, film_ior: f64) -> ThinFilm {
+        ThinFilm {
+            base,
+            thickness,
+            film_ior,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_thickness(base: Arc<dyn Material>, thickness: f64, film_ior: f64) -> ThinFilm {
+        ThinFilm::new(
+            base,
+            Arc::new(Solid::new(Vec3::ones() * thickness)),
+            film_ior,
+        )
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        let d = self.thickness.value_at(rec).x.max(0.0);
+        let film_ior = self.film_ior;
+        let spectral = _r_in.spectral;
+        let fresnel = |cos_i: f64, n1: f64, eta: Vec3, k: Vec3| match spectral {
+            Some(lambdas) => film_spectrum(cos_i, n1, film_ior, d, eta, k, lambdas),
+            None => film_reflectance(cos_i, n1, film_ior, d, eta, k),
+        };
+        self.base.scatter_with_fresnel(_r_in, rec, &fresnel, _rng)
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        self.base.emitted(rec)
+    }
+
+    fn emitted_spectrum(&self, rec: &Hitrecord, lambdas: Vec3) -> Vec3 {
+        self.base.emitted_spectrum(rec, lambdas)
+    }
+
+    fn opacity(&self, rec: &Hitrecord) -> f64 {
+        self.base.opacity(rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.base.visible_to_camera()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Conductor, Dielectric};
+    use crate::microfacet::fresnel_dielectric;
+
+    #[test]
+    fn test_zero_thickness() {
+        // 膜厚为 0 时退化为直接的菲涅尔反射
+        let r = airy_reflectance(0.7, 550.0, 1.0, 1.33, 0.0, Complex::real(1.5));
+        assert!((r - fresnel_dielectric(0.7, 1.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_quarter_wave() {
+        // 四分之一波长增透膜在正入射时几乎无反射
+        let n2 = 1.5_f64.sqrt();
+        let d = 550.0 / (4.0 * n2);
+        let r = airy_reflectance(1.0, 550.0, 1.0, n2, d, Complex::real(1.5));
+        assert!(r < 1e-9);
+    }
+
+    fn record(mat: Arc<dyn Material>) -> Hitrecord {
+        Hitrecord::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0), 1.0, mat)
+    }
+
+    #[test]
+    fn test_bubble_transmits_through_base() {
+        // 肥皂泡：透射方向不变，并由基底压入介质栈
+        let film: Arc<dyn Material> = Arc::new(ThinFilm::with_thickness(
+            Arc::new(Dielectric::new(1.0)),
+            300.0,
+            1.33,
+        ));
+        let rec = record(film.clone());
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut rng = rand::thread_rng();
+        let s = loop {
+            let s = film.scatter(&down, &rec, &mut rng).unwrap();
+            if s.ray.drc.z < 0.0 {
+                break s;
+            }
+        };
+        assert!((s.ray.drc.unit() - down.drc).length() < 1e-9);
+        assert!(s.ray.media.and_then(|m| m.top()).is_some());
+    }
+
+    #[test]
+    fn test_conductor_only_reflects() {
+        let f0 = Vec3::new(0.9, 0.6, 0.3);
+        let film: Arc<dyn Material> = Arc::new(ThinFilm::with_thickness(
+            Arc::new(Conductor::from_reflectance(f0, 0.0)),
+            250.0,
+            1.5,
+        ));
+        let rec = record(film.clone());
+        let down = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut rng = rand::thread_rng();
+        for _ in 0..32 {
+            let s = film.scatter(&down, &rec, &mut rng).unwrap();
+            assert!(s.ray.drc.z > 0.0);
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use crate::aabb::AABB;
+use crate::hittable::{Boxes, HeterogeneousMedium, Hitrecord, Object};
+use crate::material::{Isotropic, Material, Scatter};
+use crate::ray::Ray;
+use crate::spectrum::blackbody_rgb;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use rand::rngs::ThreadRng;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+/*
+体素文件格式（小端序）：
+    4 字节    魔数 "VOXL"
+    3 x u32   nx ny nz
+    f32 * (nx * ny * nz)  体素值，x 变化最快，其次 y，最后 z
+raw 文件只有最后的 f32 数组，尺寸由调用者给出
+*/
+
+pub trait DensityGrid: Send + Sync {
+    fn dims(&self) -> (usize, usize, usize);
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64;
+    fn max_value(&self) -> f64;
+
+    // 三线性插值，uvw ∈ [0, 1]^3 映射到整个网格，网格外为 0
+    fn sample(&self, uvw: Vec3) -> f64 {
+        if uvw.x < 0.0 || uvw.x > 1.0 || uvw.y < 0.0 || uvw.y > 1.0 || uvw.z < 0.0 || uvw.z > 1.0 {
+            return 0.0;
+        }
+        let (nx, ny, nz) = self.dims();
+        let x = uvw.x * (nx - 1) as f64;
+        let y = uvw.y * (ny - 1) as f64;
+        let z = uvw.z * (nz - 1) as f64;
+        let i = (x as usize).min(nx.max(2) - 2);
+        let j = (y as usize).min(ny.max(2) - 2);
+        let k = (z as usize).min(nz.max(2) - 2);
+        let (fx, fy, fz) = (x - i as f64, y - j as f64, z - k as f64);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let w = (if di == 1 { fx } else { 1.0 - fx })
+                        * (if dj == 1 { fy } else { 1.0 - fy })
+                        * (if dk == 1 { fz } else { 1.0 - fz });
+                    if w > 0.0 {
+                        accum += w * self.voxel(
+                            (i + di).min(nx - 1),
+                            (j + dj).min(ny - 1),
+                            (k + dk).min(nz - 1),
+                        );
+                    }
+                }
+            }
+        }
+        accum
+    }
+}
+
+fn read_f32s(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// 体素数据的字节数；拒绝零尺寸，乘积溢出时视为损坏的文件头
+fn data_size(nx: usize, ny: usize, nz: usize) -> io::Result<usize> {
+    if nx == 0 || ny == 0 || nz == 0 {
+        return Err(invalid("voxel grid dimensions must be non-zero"));
+    }
+    nx.checked_mul(ny)
+        .and_then(|n| n.checked_mul(nz))
+        .and_then(|n| n.checked_mul(4))
+        .ok_or_else(|| invalid("voxel grid dimensions are too large"))
+}
+
+// 稠密网格
+#[derive(Clone)]
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub data: Vec<f32>,
+    max: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> VoxelGrid {
+        assert_eq!(data.len(), nx * ny * nz);
+        let max = data.iter().fold(0.0_f32, |m, &d| m.max(d)) as f64;
+        VoxelGrid {
+            nx,
+            ny,
+            nz,
+            data,
+            max,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<VoxelGrid> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < 16 || &bytes[0..4] != b"VOXL" {
+            return Err(invalid("not a VOXL voxel file"));
+        }
+        let dim =
+            |o: usize| u32::from_le_bytes([bytes[o], bytes[o + 1], bytes[o + 2], bytes[o + 3]]);
+        let (nx, ny, nz) = (dim(4) as usize, dim(8) as usize, dim(12) as usize);
+        if bytes.len() - 16 != data_size(nx, ny, nz)? {
+            return Err(invalid("voxel data size does not match header"));
+        }
+        Ok(VoxelGrid::new(nx, ny, nz, read_f32s(&bytes[16..])))
+    }
+
+    #[allow(dead_code)]
+    pub fn load_raw(path: &Path, nx: usize, ny: usize, nz: usize) -> io::Result<VoxelGrid> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        if bytes.len() != data_size(nx, ny, nz)? {
+            return Err(invalid("raw voxel file size does not match dimensions"));
+        }
+        Ok(VoxelGrid::new(nx, ny, nz, read_f32s(&bytes)))
+    }
+}
+
+impl DensityGrid for VoxelGrid {
+    fn dims(&self) -> (usize, usize, usize) {
+        (self.nx, self.ny, self.nz)
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        self.data[(k * self.ny + j) * self.nx + i] as f64
+    }
+
+    fn max_value(&self) -> f64 {
+        self.max
+    }
+}
+
+const BRICK: usize = 8;
+
+// 稀疏网格：按 8^3 分块，全为空（不超过阈值）的块不存储
+#[derive(Clone)]
+pub struct SparseGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    bx: usize,
+    by: usize,
+    bricks: Vec<Option<Vec<f32>>>,
+    max: f64,
+}
+
+impl SparseGrid {
+    pub fn from_dense(grid: &VoxelGrid, threshold: f32) -> SparseGrid {
+        let bx = (grid.nx + BRICK - 1) / BRICK;
+        let by = (grid.ny + BRICK - 1) / BRICK;
+        let bz = (grid.nz + BRICK - 1) / BRICK;
+        let mut bricks = Vec::with_capacity(bx * by * bz);
+        for kb in 0..bz {
+            for jb in 0..by {
+                for ib in 0..bx {
+                    let mut brick = vec![0.0_f32; BRICK * BRICK * BRICK];
+                    let mut empty = true;
+                    for k in 0..BRICK {
+                        for j in 0..BRICK {
+                            for i in 0..BRICK {
+                                let (gi, gj, gk) = (ib * BRICK + i, jb * BRICK + j, kb * BRICK + k);
+                                if gi < grid.nx && gj < grid.ny && gk < grid.nz {
+                                    let d = grid.data[(gk * grid.ny + gj) * grid.nx + gi];
+                                    brick[(k * BRICK + j) * BRICK + i] = d;
+                                    empty &= d <= threshold;
+                                }
+                            }
+                        }
+                    }
+                    bricks.push(if empty { None } else { Some(brick) });
+                }
+            }
+        }
+        SparseGrid {
+            nx: grid.nx,
+            ny: grid.ny,
+            nz: grid.nz,
+            bx,
+            by,
+            bricks,
+            max: grid.max,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn load(path: &Path, threshold: f32) -> io::Result<SparseGrid> {
+        Ok(SparseGrid::from_dense(&VoxelGrid::load(path)?, threshold))
+    }
+}
+
+impl DensityGrid for SparseGrid {
+    fn dims(&self) -> (usize, usize, usize) {
+        (self.nx, self.ny, self.nz)
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        let b = ((k / BRICK) * self.by + j / BRICK) * self.bx + i / BRICK;
+        match &self.bricks[b] {
+            Some(brick) => brick[((k % BRICK) * BRICK + j % BRICK) * BRICK + i % BRICK] as f64,
+            None => 0.0,
+        }
+    }
+
+    fn max_value(&self) -> f64 {
+        self.max
+    }
+}
+
+// 把网格放进世界坐标盒 [p0, p1]，作为纹理使用（可直接驱动 HeterogeneousMedium）
+#[derive(Clone)]
+pub struct GridTexture {
+    pub grid: Arc<dyn DensityGrid>,
+    pub p0: Vec3,
+    pub p1: Vec3,
+    pub scale: f64,
+}
+
+impl GridTexture {
+    pub fn new(grid: Arc<dyn DensityGrid>, p0: Vec3, p1: Vec3, scale: f64) -> GridTexture {
+        GridTexture {
+            grid,
+            p0,
+            p1,
+            scale,
+        }
+    }
+
+    pub fn scalar(&self, p: Vec3) -> f64 {
+        let d = self.p1 - self.p0;
+        let uvw = Vec3::new(
+            (p.x - self.p0.x) / d.x,
+            (p.y - self.p0.y) / d.y,
+            (p.z - self.p0.z) / d.z,
+        );
+        self.scale * self.grid.sample(uvw)
+    }
+}
+
+impl Texture for GridTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.scalar(p)
+    }
+}
+
+// 黑体颜色查找表，按 100K 间隔
+const KELVIN_STEP: f64 = 100.0;
+
+#[derive(Clone)]
+struct BlackbodyTable {
+    colors: Vec<Vec3>,
+}
+
+impl BlackbodyTable {
+    fn new(max_kelvin: f64) -> BlackbodyTable {
+        let n = (max_kelvin / KELVIN_STEP) as usize + 2;
+        BlackbodyTable {
+            colors: (0..n)
+                .map(|i| blackbody_rgb(i as f64 * KELVIN_STEP))
+                .collect(),
+        }
+    }
+
+    fn lookup(&self, kelvin: f64) -> Vec3 {
+        let x = (kelvin / KELVIN_STEP).max(0.0);
+        let i = (x as usize).min(self.colors.len() - 2);
+        let f = (x - i as f64).min(1.0);
+        self.colors[i] * (1.0 - f) + self.colors[i + 1] * f
+    }
+}
+
+// 火焰：散射同 inner，碰撞点按温度网格发出黑体辐射
+#[derive(Clone)]
+pub struct VolumeEmission {
+    pub inner: Arc<dyn Material>,
+    pub temperature: GridTexture,
+    pub emission: Option<GridTexture>, // 额外的强度网格，缺省为 1
+    pub emission_scale: f64,
+    table: BlackbodyTable,
+}
+
+impl VolumeEmission {
+    pub fn new(
+        inner: Arc<dyn Material>,
+        temperature: GridTexture,
+        emission: Option<GridTexture>,
+        emission_scale: f64,
+    ) -> VolumeEmission {
+        let table = BlackbodyTable::new(temperature.grid.max_value() * temperature.scale);
+        VolumeEmission {
+            inner,
+            temperature,
+            emission,
+            emission_scale,
+            table,
+        }
+    }
+}
+
+impl Material for VolumeEmission {
+    fn scatter(&self, _r_in: &Ray, rec: &Hitrecord, _rng: &mut ThreadRng) -> Option<Scatter> {
+        self.inner.scatter(_r_in, rec, _rng)
+    }
+
+    fn emitted(&self, rec: &Hitrecord) -> Vec3 {
+        let intensity = match &self.emission {
+            Some(e) => e.scalar(rec.p),
+            None => 1.0,
+        };
+        self.table.lookup(self.temperature.scalar(rec.p)) * (intensity * self.emission_scale)
+    }
+}
+
+// 体素体积：边界为网格所在的盒子，内部用 HeterogeneousMedium 的 delta tracking
+#[derive(Clone)]
+pub struct VoxelVolume {
+    pub medium: HeterogeneousMedium,
+}
+
+impl VoxelVolume {
+    #[allow(dead_code)]
+    pub fn new(
+        grid: Arc<dyn DensityGrid>,
+        p0: Vec3,
+        p1: Vec3,
+        density_scale: f64,
+        albedo: Arc<dyn Texture>,
+    ) -> VoxelVolume {
+        let phase = Arc::new(Isotropic::new(albedo));
+        VoxelVolume::new_with_phase(grid, p0, p1, density_scale, phase)
+    }
+
+    pub fn new_with_phase(
+        grid: Arc<dyn DensityGrid>,
+        p0: Vec3,
+        p1: Vec3,
+        density_scale: f64,
+        phase: Arc<dyn Material>,
+    ) -> VoxelVolume {
+        let max_density = (grid.max_value() * density_scale).max(1e-6);
+        let density = Arc::new(GridTexture::new(grid, p0, p1, density_scale));
+        let boundary = Arc::new(Boxes::new(&p0, &p1, phase.clone()));
+        VoxelVolume {
+            medium: HeterogeneousMedium::new_with_phase(boundary, density, max_density, phase),
+        }
+    }
+
+    // temperature 网格单位为开尔文
+    #[allow(clippy::too_many_arguments)]
+    #[allow(dead_code)]
+    pub fn new_fire(
+        grid: Arc<dyn DensityGrid>,
+        temperature: Arc<dyn DensityGrid>,
+        emission: Option<Arc<dyn DensityGrid>>,
+        p0: Vec3,
+        p1: Vec3,
+        density_scale: f64,
+        emission_scale: f64,
+        albedo: Arc<dyn Texture>,
+    ) -> VoxelVolume {
+        let phase = Arc::new(VolumeEmission::new(
+            Arc::new(Isotropic::new(albedo)),
+            GridTexture::new(temperature, p0, p1, 1.0),
+            emission.map(|e| GridTexture::new(e, p0, p1, 1.0)),
+            emission_scale,
+        ));
+        VoxelVolume::new_with_phase(grid, p0, p1, density_scale, phase)
+    }
+}
+
+impl Object for VoxelVolume {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hitrecord> {
+        self.medium.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.medium.bounding_box(t0, t1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("raytracer_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn voxl(nx: u32, ny: u32, nz: u32, data: &[f32]) -> Vec<u8> {
+        let mut bytes = b"VOXL".to_vec();
+        for d in [nx, ny, nz].iter() {
+            bytes.extend_from_slice(&d.to_le_bytes());
+        }
+        for v in data {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn ramp(n: usize) -> Vec<f32> {
+        (0..n).map(|i| i as f32 * 0.5).collect()
+    }
+
+    #[test]
+    fn test_voxl_round_trip() {
+        let data = ramp(2 * 3 * 4);
+        let path = temp_file("round_trip.voxl", &voxl(2, 3, 4, &data));
+        let grid = VoxelGrid::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(grid.dims(), (2, 3, 4));
+        assert_eq!(grid.data, data);
+        assert_eq!(grid.voxel(1, 2, 3), 11.5);
+        assert_eq!(grid.max_value(), 11.5);
+    }
+
+    #[test]
+    fn test_voxl_bad_header() {
+        let data = ramp(8);
+        let cases = [
+            ("magic.voxl", {
+                let mut b = voxl(2, 2, 2, &data);
+                b[0] = b'X';
+                b
+            }),
+            ("short.voxl", voxl(2, 2, 2, &data)[..12].to_vec()),
+            ("size.voxl", voxl(2, 2, 3, &data)),
+            ("zero.voxl", voxl(0, 2, 2, &[])),
+            ("overflow.voxl", voxl(u32::MAX, u32::MAX, u32::MAX, &data)),
+        ];
+        for (name, bytes) in cases.iter() {
+            let path = temp_file(name, bytes);
+            let err = VoxelGrid::load(&path).err();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(err.map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        }
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        let data = ramp(3 * 2 * 2);
+        let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+        let path = temp_file("round_trip.raw", &bytes);
+        let grid = VoxelGrid::load_raw(&path, 3, 2, 2).unwrap();
+        let mismatch = VoxelGrid::load_raw(&path, 2, 2, 2).err();
+        let zero = VoxelGrid::load_raw(&path, 0, 2, 2).err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(grid.data, data);
+        assert_eq!(mismatch.map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        assert_eq!(zero.map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_sparse_matches_dense() {
+        // 尺寸不是 8 的倍数；只有一个角落里的块非空
+        let (nx, ny, nz) = (10, 9, 17);
+        let mut data = vec![0.0_f32; nx * ny * nz];
+        data[(16 * ny + 8) * nx + 9] = 2.0;
+        data[(ny + 2) * nx + 3] = 0.05;
+        let dense = VoxelGrid::new(nx, ny, nz, data);
+        let sparse = SparseGrid::from_dense(&dense, 0.1);
+        assert_eq!(sparse.dims(), (nx, ny, nz));
+        assert_eq!(sparse.bricks.len(), 2 * 2 * 3);
+        assert_eq!(sparse.bricks.iter().filter(|b| b.is_some()).count(), 1);
+        assert_eq!(sparse.voxel(9, 8, 16), 2.0);
+        // 低于阈值的块整块丢弃
+        assert_eq!(sparse.voxel(3, 2, 1), 0.0);
+        assert_eq!(sparse.max_value(), 2.0);
+        let uvw = Vec3::new(0.95, 0.9, 0.98);
+        assert_eq!(sparse.sample(uvw), dense.sample(uvw));
+    }
+}